@@ -11,17 +11,9 @@ txid=$(cardaminal tx create $wallet)
 # send the specified amount to the external address
 cardaminal tx edit $wallet $txid add-output $to_address $to_transfer
 
-# select an utxo from our wallet to use as input
-input=$(cardaminal wallet select $wallet --min-lovelace $to_transfer)
-cardaminal tx edit $wallet $txid add-input $input
-
-# define the fee for the tx (TODO: fee estimation via cardaminal command)
-cardaminal tx edit $wallet $txid set-fee 3000000
-
-# return the remaining value back to my address
-change=$(cardaminal tx balance $wallet $txid -a)
-my_address=$(cardaminal wallet address $wallet --testnet)
-cardaminal tx edit $wallet $txid add-output $my_address $change
+# select utxos from our wallet as inputs, compute the fee and return the
+# remaining value back to our address
+cardaminal tx edit $wallet $txid auto-balance
 
 # print the tx as json
 cardaminal tx inspect $wallet $txid | jq
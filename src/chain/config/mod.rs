@@ -4,13 +4,95 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Duration, Local, TimeZone, Utc};
 use comfy_table::Table;
-use miette::{Context, IntoDiagnostic};
+use miette::{miette, Context, IntoDiagnostic};
 use pallas::crypto::hash::Hash;
 use serde::{Deserialize, Serialize};
 
-use crate::utils::{deserialize_date, serialize_date, OutputFormatter};
+use crate::{
+    utils::{deserialize_date, serialize_date, OutputFormatter},
+    wallet::config::DEFAULT_SUBMIT_API_URL,
+};
+
+/// Slot timing of a network: unix time of the first slot, Byron slot length
+/// in seconds and the slot at which Shelley (1 second slots) started
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SlotTiming {
+    pub system_start: i64,
+    pub byron_slot_length: u64,
+    pub shelley_start_slot: u64,
+}
+
+const MAINNET_TIMING: SlotTiming = SlotTiming {
+    system_start: 1506203091,
+    byron_slot_length: 20,
+    shelley_start_slot: 4492800,
+};
+
+const PREPROD_TIMING: SlotTiming = SlotTiming {
+    system_start: 1654041600,
+    byron_slot_length: 20,
+    shelley_start_slot: 86400,
+};
+
+const PREVIEW_TIMING: SlotTiming = SlotTiming {
+    system_start: 1666656000,
+    byron_slot_length: 20,
+    shelley_start_slot: 0,
+};
+
+const SANCHONET_TIMING: SlotTiming = SlotTiming {
+    system_start: 1686789000,
+    byron_slot_length: 20,
+    shelley_start_slot: 0,
+};
+
+/// Parameters of a well-known network used to create chains without
+/// specifying them by hand
+pub struct NetworkPreset {
+    pub magic: &'static str,
+    pub address_network_id: u8,
+    pub upstream: &'static str,
+    pub timing: SlotTiming,
+    /// default submit api, only preprod has a public one. Chains of the other
+    /// networks have to be given one with `--submit-api`.
+    pub submit_api: Option<&'static str>,
+}
+
+pub const MAINNET: NetworkPreset = NetworkPreset {
+    magic: "764824073",
+    address_network_id: 1,
+    upstream: "backbone.cardano.iog.io:3001",
+    timing: MAINNET_TIMING,
+    submit_api: None,
+};
+
+pub const PREPROD: NetworkPreset = NetworkPreset {
+    magic: "1",
+    address_network_id: 0,
+    upstream: "preprod-node.play.dev.cardano.org:3001",
+    timing: PREPROD_TIMING,
+    submit_api: Some(DEFAULT_SUBMIT_API_URL),
+};
+
+pub const PREVIEW: NetworkPreset = NetworkPreset {
+    magic: "2",
+    address_network_id: 0,
+    upstream: "preview-node.play.dev.cardano.org:3001",
+    timing: PREVIEW_TIMING,
+    submit_api: None,
+};
+
+pub const SANCHONET: NetworkPreset = NetworkPreset {
+    magic: "4",
+    address_network_id: 0,
+    upstream: "sanchonet-node.play.dev.cardano.org:3001",
+    timing: SANCHONET_TIMING,
+    submit_api: None,
+};
+
+const PRESETS: [&NetworkPreset; 4] = [&MAINNET, &PREPROD, &PREVIEW, &SANCHONET];
 
 #[derive(Serialize, Deserialize)]
 pub struct Chain {
@@ -20,6 +102,14 @@ pub struct Chain {
     pub magic: String,
     pub address_network_id: u8,
     pub after: Option<ChainAfter>,
+    /// slot timing of the network, known networks fall back to built-in
+    /// values when it isn't set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timing: Option<SlotTiming>,
+    /// submit api url used by wallets attached to the chain which don't set
+    /// their own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub submit_api: Option<String>,
 
     #[serde(serialize_with = "serialize_date")]
     #[serde(deserialize_with = "deserialize_date")]
@@ -42,6 +132,8 @@ impl Chain {
             upstream,
             magic,
             after,
+            timing: None,
+            submit_api: None,
         }
     }
 
@@ -84,6 +176,87 @@ impl Chain {
         Self::dir(root_dir, name).join("db")
     }
 
+    fn slot_timing(&self) -> Option<SlotTiming> {
+        if self.timing.is_some() {
+            return self.timing;
+        }
+
+        self.preset().map(|x| x.timing)
+    }
+
+    /// The well-known network this chain points at, matched by its magic
+    pub fn preset(&self) -> Option<&'static NetworkPreset> {
+        PRESETS.into_iter().find(|x| x.magic == self.magic)
+    }
+
+    /// Wall-clock time of a slot, only known when the slot timing of the
+    /// network is
+    pub fn slot_to_time(&self, slot: u64) -> Option<DateTime<Utc>> {
+        let timing = self.slot_timing()?;
+
+        let byron_slots = slot.min(timing.shelley_start_slot);
+        let shelley_slots = slot.saturating_sub(timing.shelley_start_slot);
+        let elapsed = byron_slots * timing.byron_slot_length + shelley_slots;
+
+        Utc.timestamp_opt(timing.system_start + elapsed as i64, 0)
+            .single()
+    }
+
+    /// Slot in progress at a wall-clock time, rounded down to the start of
+    /// the slot
+    pub fn time_to_slot(&self, time: DateTime<Utc>) -> Option<u64> {
+        let timing = self.slot_timing()?;
+
+        let elapsed = u64::try_from(time.timestamp() - timing.system_start).ok()?;
+        let byron_duration = timing.shelley_start_slot * timing.byron_slot_length;
+
+        if elapsed < byron_duration {
+            Some(elapsed / timing.byron_slot_length)
+        } else {
+            Some(timing.shelley_start_slot + elapsed - byron_duration)
+        }
+    }
+
+    /// Parse a slot given as a number, an RFC 3339 / ISO-8601 timestamp or a
+    /// duration from now like `+2h` or `+1d12h`
+    pub fn parse_slot(&self, value: &str) -> miette::Result<u64> {
+        if let Ok(slot) = value.parse() {
+            return Ok(slot);
+        }
+
+        let time = match value.strip_prefix('+') {
+            Some(duration) => {
+                Utc::now()
+                    + parse_duration(duration).ok_or(miette!(
+                        "invalid duration '{duration}', expected something like 2h or 1d12h30m"
+                    ))?
+            }
+            None => DateTime::parse_from_rfc3339(value)
+                .into_diagnostic()
+                .context(
+                    "expected a slot, a timestamp like 2024-01-31T12:00:00Z or a duration like +2h",
+                )?
+                .with_timezone(&Utc),
+        };
+
+        self.time_to_slot(time).ok_or(miette!(
+            "slot timing of chain '{}' unknown, slots can only be given as numbers",
+            self.name
+        ))
+    }
+
+    /// Slot timing as expected by the Plutus script evaluator: unix time of
+    /// the first Shelley slot in milliseconds, that slot and the slot length
+    /// in milliseconds
+    pub fn plutus_slot_config(&self) -> Option<(u64, u64, u32)> {
+        let timing = self.slot_timing()?;
+
+        let zero_time =
+            timing.system_start as u64 + timing.shelley_start_slot * timing.byron_slot_length;
+
+        Some((zero_time * 1000, timing.shelley_start_slot, 1000))
+    }
+
     pub fn list_available(root_dir: &Path) -> miette::Result<Vec<String>> {
         let parent = root_dir
             .join("chains")
@@ -101,6 +274,39 @@ impl Chain {
     }
 }
 
+/// Duration made of amounts of days, hours, minutes and seconds, like
+/// `1d12h` or `90s`
+fn parse_duration(value: &str) -> Option<Duration> {
+    let mut total = Duration::zero();
+    let mut amount = String::new();
+
+    for c in value.chars() {
+        if c.is_ascii_digit() {
+            amount.push(c);
+            continue;
+        }
+
+        let n: i64 = amount.parse().ok()?;
+        amount.clear();
+
+        total = total
+            + match c {
+                'd' => Duration::days(n),
+                'h' => Duration::hours(n),
+                'm' => Duration::minutes(n),
+                's' => Duration::seconds(n),
+                _ => return None,
+            };
+    }
+
+    // every amount needs a unit
+    if !amount.is_empty() || value.is_empty() {
+        return None;
+    }
+
+    Some(total)
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct ChainUpstream {
     pub address: String,
@@ -160,3 +366,46 @@ impl OutputFormatter for Vec<Chain> {
         println!("{json}");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slot_time_round_trip() {
+        let chain = Chain::new(
+            "preprod".into(),
+            "1".into(),
+            0,
+            ChainUpstream {
+                address: "localhost:3001".into(),
+            },
+            None,
+        );
+
+        for slot in [0, 86399, 86400, 50_000_000] {
+            assert_eq!(
+                chain.time_to_slot(chain.slot_to_time(slot).unwrap()),
+                Some(slot)
+            );
+        }
+
+        assert_eq!(chain.parse_slot("1234").unwrap(), 1234);
+        assert_eq!(
+            chain.parse_slot("2022-06-01T00:00:20Z").unwrap(),
+            chain.parse_slot("2022-06-01T02:00:20+02:00").unwrap()
+        );
+        assert!(chain.parse_slot("+2x").is_err());
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("2h"), Some(Duration::hours(2)));
+        assert_eq!(
+            parse_duration("1d12h30m"),
+            Some(Duration::days(1) + Duration::hours(12) + Duration::minutes(30))
+        );
+        assert_eq!(parse_duration("90"), None);
+        assert_eq!(parse_duration(""), None);
+    }
+}
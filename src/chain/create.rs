@@ -1,30 +1,40 @@
 use std::{fs, io::Write};
 
-use clap::Parser;
-use miette::{bail, IntoDiagnostic};
+use clap::{Parser, ValueEnum};
+use miette::{bail, miette, IntoDiagnostic};
 use pallas::{
     crypto::hash::Hash,
     network::{facades::PeerClient, miniprotocols::Point},
 };
-use tracing::{info, instrument};
+use tracing::{info, instrument, warn};
 
-use crate::chain::config::{Chain, ChainUpstream};
+use crate::chain::config::{Chain, ChainUpstream, NetworkPreset};
 
-use super::config::ChainAfter;
+use super::config::{self, ChainAfter};
 
 #[derive(Parser)]
 pub struct Args {
     /// friendly name to identify the chain
     pub name: String,
 
-    /// [host]:[port] of the upstream node
-    pub upstream: String,
+    /// [host]:[port] of the upstream node, defaults to a relay of the
+    /// network preset
+    pub upstream: Option<String>,
 
-    /// network magic of the chain
-    pub magic: String,
+    /// network magic of the chain, defaults to the one of the network preset
+    pub magic: Option<String>,
 
-    /// network id for addresses
-    pub address_network_id: u8,
+    /// network id for addresses, defaults to the one of the network preset
+    pub address_network_id: Option<u8>,
+
+    /// well-known network whose parameters are used for the ones not given
+    #[arg(long)]
+    pub network: Option<Network>,
+
+    /// url of the submit api used by wallets attached to the chain, required
+    /// to submit transactions on any network but preprod
+    #[arg(long)]
+    pub submit_api: Option<String>,
 
     /// [slot],[hash] of the sync start point
     #[arg(short, long)]
@@ -34,26 +44,77 @@ pub struct Args {
     pub after_tip: bool,
 }
 
+#[derive(ValueEnum, Clone)]
+pub enum Network {
+    Mainnet,
+    Preprod,
+    Preview,
+    Sanchonet,
+}
+
+impl Network {
+    fn preset(&self) -> &'static NetworkPreset {
+        match self {
+            Network::Mainnet => &config::MAINNET,
+            Network::Preprod => &config::PREPROD,
+            Network::Preview => &config::PREVIEW,
+            Network::Sanchonet => &config::SANCHONET,
+        }
+    }
+}
+
 #[instrument("create", skip_all, fields(name=args.name))]
 pub async fn run(args: Args, ctx: &crate::Context) -> miette::Result<()> {
+    let preset = args.network.as_ref().map(Network::preset);
+
+    let upstream = args
+        .upstream
+        .or(preset.map(|x| x.upstream.to_string()))
+        .ok_or(miette!("upstream is required unless a network is given"))?;
+
+    let magic = args
+        .magic
+        .or(preset.map(|x| x.magic.to_string()))
+        .ok_or(miette!("magic is required unless a network is given"))?;
+
+    let address_network_id = args
+        .address_network_id
+        .or(preset.map(|x| x.address_network_id))
+        .ok_or(miette!(
+            "address network id is required unless a network is given"
+        ))?;
+
+    if let Some(preset) = preset {
+        if magic != preset.magic || address_network_id != preset.address_network_id {
+            warn!("magic or address network id differ from the ones of the network preset");
+        }
+    }
+
     let after = match (args.after, args.after_tip) {
         (Some(after), _) => ChainAfter::try_from(after)?.into(),
-        (_, true) => find_tip(&args.upstream, &args.magic).await?.into(),
+        (_, true) => find_tip(&upstream, &magic).await?.into(),
         _ => None,
     };
 
     let chain_slug = slug::slugify(&args.name);
 
-    let chain = Chain::new(
+    let mut chain = Chain::new(
         args.name,
-        args.magic,
-        args.address_network_id,
-        ChainUpstream {
-            address: args.upstream,
-        },
+        magic,
+        address_network_id,
+        ChainUpstream { address: upstream },
         after,
     );
 
+    chain.timing = preset.map(|x| x.timing);
+    chain.submit_api = args
+        .submit_api
+        .or(preset.and_then(|x| x.submit_api.map(String::from)));
+
+    if chain.submit_api.is_none() {
+        warn!("no submit api for the chain, wallets attached to it can't submit transactions unless they set their own, pass one with --submit-api");
+    }
+
     let chain_path = ctx.dirs.root_dir.join("chains").join(&chain_slug);
 
     if chain_path.exists() {
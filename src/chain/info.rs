@@ -1,5 +1,5 @@
 use clap::Parser;
-use miette::bail;
+use miette::{bail, IntoDiagnostic};
 use tracing::instrument;
 
 use super::config;
@@ -14,14 +14,26 @@ pub struct Args {
 pub async fn run(args: Args, ctx: &crate::Context) -> miette::Result<()> {
     let cfg = config::Chain::load_config(&ctx.dirs.root_dir, &args.name)?;
 
-    if cfg.is_none() {
+    let Some(chain) = cfg else {
         bail!("chain doesn't exist");
-    }
+    };
 
     let dir = config::Chain::dir(&ctx.dirs.root_dir, &args.name);
     println!("local storage dir: {}", &dir.to_string_lossy());
 
-    println!("{}", serde_json::to_string(&cfg).unwrap());
+    println!("{}", serde_json::to_string(&chain).unwrap());
+
+    let tip = config::Chain::load_db(&ctx.dirs.root_dir, &args.name)?
+        .find_tip()
+        .into_diagnostic()?;
+
+    match tip {
+        Some((slot, _)) => match chain.slot_to_time(slot) {
+            Some(time) => println!("tip: slot {slot} ({})", time.to_rfc3339()),
+            None => println!("tip: slot {slot}"),
+        },
+        None => println!("tip: chain not synced yet"),
+    }
 
     Ok(())
 }
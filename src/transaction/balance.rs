@@ -9,6 +9,8 @@ use crate::wallet::{
     dal::{entities::transaction::Status, WalletDB},
 };
 
+use super::{pparams::ProtocolParams, staging::StagingTx};
+
 #[derive(Parser)]
 pub struct Args {
     /// name of the wallet
@@ -42,13 +44,17 @@ pub async fn run(args: Args, ctx: &crate::Context) -> miette::Result<()> {
         bail!("balance can only be called during building")
     }
 
-    let tx: StagingTransaction = serde_json::from_slice(&record.tx_json).into_diagnostic()?;
+    let staging: StagingTx = serde_json::from_slice(&record.tx_json).into_diagnostic()?;
+    let tx = &staging.tx;
+
+    let pparams = ProtocolParams::load(&wallet_db).await?;
 
-    let total_inputs = compute_total_input(&tx, &wallet_db).await?;
-    let total_outputs = compute_total_output(&tx);
+    let total_inputs = compute_total_input(tx, &wallet_db).await?;
+    let total_outputs = compute_total_output(tx);
+    let implicit = staging.implicit_lovelace(pparams.key_deposit);
     let fee = tx.fee.unwrap_or_default();
 
-    let mut result = (total_inputs as i128) - (total_outputs as i128) - (fee as i128);
+    let mut result = (total_inputs as i128) + implicit - (total_outputs as i128) - (fee as i128);
 
     if args.absolute {
         result = result.abs();
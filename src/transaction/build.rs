@@ -1,12 +1,33 @@
+use std::{cmp::Reverse, path::Path};
+
 use clap::Parser;
-use miette::{bail, IntoDiagnostic};
+use miette::{bail, miette, Context, IntoDiagnostic};
+use pallas::{
+    crypto::hash::{Hash, Hasher},
+    ledger::{
+        addresses::Address,
+        primitives::babbage::{self, MintedDatumOption},
+        traverse::{Era, MultiEraOutput},
+    },
+    txbuilder::{Input, Output, ScriptKind},
+};
+use sea_orm::Order;
 use tracing::{info, instrument};
 
-use pallas::txbuilder::{BuildBabbage, StagingTransaction};
+use crate::{
+    chain::config::Chain,
+    wallet::{
+        config::Wallet,
+        dal::{entities::transaction::Status, WalletDB},
+        multisig,
+    },
+};
 
-use crate::wallet::{
-    config::Wallet,
-    dal::{entities::transaction::Status, WalletDB},
+use super::{
+    eval, fee,
+    pparams::ProtocolParams,
+    staging::{self, PlutusVersion, StagingTx},
+    validate,
 };
 
 #[derive(Parser)]
@@ -17,6 +38,15 @@ pub struct Args {
 
     /// transaction id
     id: i32,
+
+    /// keep the execution units set on the redeemers instead of evaluating
+    /// the Plutus scripts
+    #[arg(long, action)]
+    no_eval: bool,
+
+    /// skip checking the built transaction against the ledger rules
+    #[arg(long, action)]
+    no_validate: bool,
 }
 
 #[instrument("build", skip_all, fields())]
@@ -38,13 +68,51 @@ pub async fn run(args: Args, ctx: &crate::Context) -> miette::Result<()> {
         bail!("can only build transactions in staging state")
     }
 
-    let tx: StagingTransaction = serde_json::from_slice(&record.tx_json).into_diagnostic()?;
+    let mut staging: StagingTx = serde_json::from_slice(&record.tx_json).into_diagnostic()?;
+
+    attach_multisig_script(&mut staging, &wallet, &wallet_db).await?;
+
+    check_datums(&staging, &wallet_db).await?;
+
+    let pparams = ProtocolParams::load(&wallet_db).await?;
+
+    check_outputs(&staging, &pparams)?;
+
+    staging.cost_models = plutus_cost_models(&staging, &wallet_db, &pparams).await?;
+
+    if !args.no_eval {
+        evaluate_scripts(
+            &mut staging,
+            &wallet,
+            &wallet_db,
+            &pparams,
+            &ctx.dirs.root_dir,
+        )
+        .await?;
+    }
+
+    prepare_collateral(
+        &mut staging,
+        &wallet,
+        &wallet_db,
+        &pparams,
+        &ctx.dirs.root_dir,
+    )
+    .await?;
+
+    if staging.tx.fee.is_none() {
+        let fee = fee::estimate_fee(&staging, &wallet_db, &pparams).await?;
 
-    // TODO: `build` will take protocol parameters from wallet db for validated tx building
+        info!(fee, "computed tx fee");
 
-    let built_tx = tx
-        .build_babbage_raw()
-        .map_err(|e| miette::miette!("tx build failed: {e:?}"))?;
+        staging.tx = staging.tx.fee(fee);
+    }
+
+    let built_tx = staging.build()?;
+
+    if !args.no_validate {
+        validate::validate(&built_tx, &wallet_db, &pparams, false).await?;
+    }
 
     record.status = Status::Built;
     record.tx_json = serde_json::to_vec(&built_tx).into_diagnostic()?;
@@ -59,3 +127,658 @@ pub async fn run(args: Args, ctx: &crate::Context) -> miette::Result<()> {
 
     Ok(())
 }
+
+/// Attach the native script of a multisig wallet when the transaction spends
+/// outputs locked by it
+pub async fn attach_multisig_script(
+    staging: &mut StagingTx,
+    wallet: &Wallet,
+    wallet_db: &WalletDB,
+) -> miette::Result<()> {
+    let script = match wallet.keys.multisig_script()? {
+        Some(x) => x,
+        None => return Ok(()),
+    };
+
+    let hash = multisig::hash(&script);
+
+    let mut spends_script = false;
+
+    for input in staging.tx.inputs.iter().flatten() {
+        let resolved = wallet_db
+            .resolve_utxo(&input.tx_hash.0, input.txo_index as i32)
+            .await
+            .into_diagnostic()
+            .context("resolving input")?;
+
+        if resolved.is_some_and(|x| x.payment_cred == hash.to_vec()) {
+            spends_script = true;
+            break;
+        }
+    }
+
+    if spends_script {
+        staging.tx =
+            std::mem::take(&mut staging.tx).script(ScriptKind::Native, multisig::to_cbor(&script)?);
+
+        info!(script = hash.to_string(), "attached multisig script");
+    }
+
+    Ok(())
+}
+
+/// Check that every spent script output locked with a datum hash has its
+/// datum either in the witness set or inline in a reference input. Inputs
+/// unknown to the wallet can't be checked and are skipped.
+pub async fn check_datums(staging: &StagingTx, wallet_db: &WalletDB) -> miette::Result<()> {
+    let tx = &staging.tx;
+
+    let mut available: Vec<Hash<32>> = tx
+        .datums
+        .iter()
+        .flat_map(|x| x.keys())
+        .map(|x| Hash::from(x.0))
+        .collect();
+
+    let inputs = tx
+        .reference_inputs
+        .iter()
+        .flatten()
+        .map(|x| (x, true))
+        .chain(tx.inputs.iter().flatten().map(|x| (x, false)));
+
+    // reference inputs come first so their inline datums are known when
+    // checking the spent inputs
+    let mut locked = vec![];
+
+    for (input, reference) in inputs {
+        let resolved = wallet_db
+            .resolve_utxo(&input.tx_hash.0, input.txo_index as i32)
+            .await
+            .into_diagnostic()
+            .context("resolving input")?;
+
+        let Some(utxo) = resolved else { continue };
+
+        if !reference && !utxo.is_script() {
+            continue;
+        }
+
+        let era = Era::try_from(utxo.era)
+            .into_diagnostic()
+            .context("parsing utxo era")?;
+
+        let output = MultiEraOutput::decode(era, &utxo.cbor)
+            .into_diagnostic()
+            .context("parsing utxo cbor")?;
+
+        match output.datum() {
+            Some(MintedDatumOption::Data(d)) if reference => {
+                available.push(Hasher::<256>::hash(d.0.raw_cbor()))
+            }
+            Some(MintedDatumOption::Hash(hash)) if !reference => locked.push((input, hash)),
+            _ => (),
+        }
+    }
+
+    for (input, hash) in locked {
+        if !available.contains(&hash) {
+            bail!(
+                "input {}#{} is locked by datum hash {hash}, add its datum to the witness set or reference an output holding it inline",
+                hex::encode(input.tx_hash.0),
+                input.txo_index
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Check that every output, collateral return included, holds the minimum
+/// lovelace for its size and that its value isn't over the maximum size
+pub fn check_outputs(staging: &StagingTx, pparams: &ProtocolParams) -> miette::Result<()> {
+    let outputs = staging
+        .tx
+        .outputs
+        .iter()
+        .flatten()
+        .enumerate()
+        .map(|(i, x)| (format!("output {i}"), x))
+        .chain(
+            staging
+                .tx
+                .collateral_output
+                .iter()
+                .map(|x| ("collateral output".to_string(), x)),
+        );
+
+    for (name, output) in outputs {
+        let min = pparams.min_lovelace(output)?;
+
+        if output.lovelace < min {
+            bail!(
+                "{name} holds {} lovelace but needs at least {min}",
+                output.lovelace
+            );
+        }
+
+        let size = pparams.value_size(output)?;
+
+        if size > pparams.max_value_size {
+            bail!(
+                "{name} value is {size} bytes, over the maximum of {} bytes, split its assets over more outputs",
+                pparams.max_value_size
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Pick the collateral of a transaction running scripts when none was
+/// chosen: the largest key locked utxos holding only lovelace, up to the
+/// maximum amount of collateral inputs. Unless a collateral return was set,
+/// the lovelace exceeding the required collateral goes back to the change
+/// address and the total collateral is declared.
+pub async fn prepare_collateral(
+    staging: &mut StagingTx,
+    wallet: &Wallet,
+    wallet_db: &WalletDB,
+    pparams: &ProtocolParams,
+    root_dir: &Path,
+) -> miette::Result<()> {
+    let tx = &staging.tx;
+
+    if tx.redeemers.is_none() {
+        return Ok(());
+    }
+
+    let manual: Vec<_> = tx
+        .collateral_inputs
+        .iter()
+        .flatten()
+        .map(staging::copy_input)
+        .collect();
+
+    if !manual.is_empty() && tx.collateral_output.is_some() {
+        return Ok(());
+    }
+
+    let return_address = match &tx.change_address {
+        Some(address) => address.0.clone(),
+        None => {
+            let chain_name = wallet.chain.as_ref().ok_or(miette!(
+                "no change address set and wallet not attached to a chain"
+            ))?;
+
+            let chain =
+                Chain::load_config(root_dir, chain_name)?.ok_or(miette!("chain doesn't exist"))?;
+
+            Address::from_bech32(wallet.addresses.for_network(chain.address_network_id))
+                .into_diagnostic()
+                .context("parsing wallet address")?
+        }
+    };
+
+    // (input, lovelace) of the collateral candidates, largest first
+
+    let mut candidates = vec![];
+
+    if manual.is_empty() {
+        let spent: Vec<_> = tx.inputs.iter().flatten().collect();
+
+        for utxo in wallet_db
+            .fetch_all_utxos(Order::Asc)
+            .await
+            .into_diagnostic()?
+        {
+            if utxo.is_script() {
+                continue;
+            }
+
+            let hash: [u8; 32] = utxo
+                .tx_hash
+                .clone()
+                .try_into()
+                .map_err(|_| miette!("malformed utxo hash in wallet db"))?;
+
+            let input = Input::new(hash.into(), utxo.txo_index as u64);
+
+            let value = validate::utxo_value(&utxo)?;
+
+            if spent.contains(&&input) || !value.assets.is_empty() {
+                continue;
+            }
+
+            candidates.push((input, value.lovelace as u64));
+        }
+
+        candidates.sort_by_key(|(_, lovelace)| Reverse(*lovelace));
+    } else {
+        for input in manual.iter() {
+            let utxo = wallet_db
+                .resolve_utxo(&input.tx_hash.0, input.txo_index as i32)
+                .await
+                .into_diagnostic()
+                .context("resolving collateral input")?
+                .ok_or(miette!("can't find collateral utxo in wallet"))?;
+
+            let value = validate::utxo_value(&utxo)?;
+
+            if !value.assets.is_empty() {
+                bail!("collateral inputs hold native assets, set the collateral return explicitly");
+            }
+
+            candidates.push((staging::copy_input(input), value.lovelace as u64));
+        }
+    }
+
+    let mut fee = match staging.tx.fee {
+        Some(x) => x,
+        None => fee::estimate_fee(staging, wallet_db, pparams).await?,
+    };
+
+    loop {
+        let required = pparams.min_collateral(fee);
+
+        let mut selected = vec![];
+        let mut provided = 0;
+
+        for (input, lovelace) in candidates.iter() {
+            if provided >= required && manual.is_empty() {
+                break;
+            }
+
+            selected.push(staging::copy_input(input));
+            provided += lovelace;
+        }
+
+        if provided < required {
+            bail!("not enough lovelace in key locked utxos to provide a collateral of {required} lovelace");
+        }
+
+        if selected.len() as u64 > pparams.max_collateral_inputs {
+            bail!(
+                "a collateral of {required} lovelace needs {} inputs, over the maximum of {}",
+                selected.len(),
+                pparams.max_collateral_inputs
+            );
+        }
+
+        let mut tx = staging::copy_staging(&staging.tx);
+        tx.collateral_inputs = None;
+
+        for input in selected {
+            tx = tx.collateral_input(input);
+        }
+
+        // an excess too small for an output of its own is left in the
+        // collateral
+        let collateral_return = Output::new(return_address.clone(), provided - required);
+
+        if provided > required
+            && collateral_return.lovelace >= pparams.min_lovelace(&collateral_return)?
+        {
+            staging.tx = tx.collateral_output(collateral_return);
+            staging.total_collateral = Some(required);
+        } else {
+            staging.tx = tx.clear_collateral_output();
+            staging.total_collateral = Some(provided);
+        }
+
+        if staging.tx.fee.is_some() {
+            break;
+        }
+
+        let needed = fee::estimate_fee(staging, wallet_db, pparams).await?;
+
+        if needed <= fee {
+            break;
+        }
+
+        fee = needed;
+    }
+
+    info!(
+        inputs = staging.tx.collateral_inputs.iter().flatten().count(),
+        total = staging.total_collateral,
+        "collateral prepared"
+    );
+
+    Ok(())
+}
+
+/// Evaluate the Plutus scripts of the transaction, if it has any redeemers,
+/// filling in their execution units
+pub async fn evaluate_scripts(
+    staging: &mut StagingTx,
+    wallet: &Wallet,
+    wallet_db: &WalletDB,
+    pparams: &ProtocolParams,
+    root_dir: &Path,
+) -> miette::Result<()> {
+    if staging.tx.redeemers.is_none() {
+        return Ok(());
+    }
+
+    let chain_name = wallet.chain.as_ref().ok_or(miette!(
+        "wallet must be attached to a chain to evaluate scripts"
+    ))?;
+
+    let chain = Chain::load_config(root_dir, chain_name)?.ok_or(miette!("chain doesn't exist"))?;
+
+    let slot_config = chain.plutus_slot_config().ok_or(miette!(
+        "slot timing of chain '{}' unknown, can't evaluate scripts",
+        chain.name
+    ))?;
+
+    eval::evaluate(staging, wallet_db, pparams, slot_config).await
+}
+
+/// Cost models of the Plutus languages of the scripts run by the
+/// transaction, either attached to it or referenced by its inputs
+pub async fn plutus_cost_models(
+    staging: &StagingTx,
+    wallet_db: &WalletDB,
+    pparams: &ProtocolParams,
+) -> miette::Result<Vec<(PlutusVersion, Vec<i64>)>> {
+    let tx = &staging.tx;
+
+    if tx.redeemers.is_none() {
+        return Ok(vec![]);
+    }
+
+    let mut versions = vec![];
+
+    for script in tx.scripts.iter().flat_map(|x| x.values()) {
+        match script.kind {
+            ScriptKind::PlutusV1 => versions.push(PlutusVersion::V1),
+            ScriptKind::PlutusV2 => versions.push(PlutusVersion::V2),
+            _ => (),
+        }
+    }
+
+    let inputs = tx
+        .inputs
+        .iter()
+        .flatten()
+        .chain(tx.reference_inputs.iter().flatten());
+
+    for input in inputs {
+        let resolved = wallet_db
+            .resolve_utxo(&input.tx_hash.0, input.txo_index as i32)
+            .await
+            .into_diagnostic()
+            .context("resolving input")?;
+
+        // any of them could hold a reference script, whose language has to
+        // be part of the script data hash
+        let Some(utxo) = resolved else {
+            bail!(
+                "can't compute the script data hash, utxo {}#{} is not known by the wallet",
+                hex::encode(input.tx_hash.0),
+                input.txo_index
+            );
+        };
+
+        let era = Era::try_from(utxo.era)
+            .into_diagnostic()
+            .context("parsing utxo era")?;
+
+        let output = MultiEraOutput::decode(era, &utxo.cbor)
+            .into_diagnostic()
+            .context("parsing utxo cbor")?;
+
+        match output.script_ref() {
+            Some(babbage::PseudoScript::PlutusV1Script(_)) => versions.push(PlutusVersion::V1),
+            Some(babbage::PseudoScript::PlutusV2Script(_)) => versions.push(PlutusVersion::V2),
+            _ => (),
+        }
+    }
+
+    if versions.is_empty() {
+        bail!(
+            "transaction has redeemers but no Plutus script attached or referenced by its inputs"
+        );
+    }
+
+    let mut cost_models = vec![];
+
+    for version in [PlutusVersion::V1, PlutusVersion::V2] {
+        if !versions.contains(&version) {
+            continue;
+        }
+
+        let model = match version {
+            PlutusVersion::V1 => &pparams.plutus_v1_cost_model,
+            PlutusVersion::V2 => &pparams.plutus_v2_cost_model,
+        };
+
+        let model = model.clone().ok_or(miette!(
+            "no Plutus{version:?} cost model in the synced protocol parameters"
+        ))?;
+
+        cost_models.push((version, model));
+    }
+
+    Ok(cost_models)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use pallas::{
+        codec::minicbor,
+        ledger::addresses::{Network, ShelleyAddress, ShelleyDelegationPart, ShelleyPaymentPart},
+        txbuilder::StagingTransaction,
+    };
+
+    use super::*;
+    use crate::wallet::config::{Addresses, Keys};
+
+    const UTXO_HASH: [u8; 32] = [9; 32];
+    const POLICY: [u8; 28] = [7; 28];
+
+    fn address(payment: ShelleyPaymentPart) -> Address {
+        ShelleyAddress::new(Network::Testnet, payment, ShelleyDelegationPart::Null).into()
+    }
+
+    fn key_address() -> Address {
+        address(ShelleyPaymentPart::key_hash([1; 28].into()))
+    }
+
+    fn wallet() -> Wallet {
+        let keys = Keys {
+            public_key_hash: hex::encode([1; 28]),
+            private_encrypted: None,
+            root_encrypted: None,
+            account_public: None,
+            stake_key_hash: None,
+            stake_private_encrypted: None,
+            multisig_script: None,
+        };
+
+        let addresses = Addresses::new(
+            ShelleyPaymentPart::key_hash([1; 28].into()),
+            ShelleyDelegationPart::Null,
+        )
+        .unwrap();
+
+        Wallet::new("test".into(), keys, addresses, None)
+    }
+
+    /// Wallet db holding, at indexes 0 to 4, 10 ada, 2 ada, 250000 lovelace,
+    /// a script locked 5 ada and 3 ada along with a token
+    async fn wallet_db(name: &str) -> (WalletDB, PathBuf) {
+        // start afresh if an earlier failing run left its db behind
+        let dir = std::env::temp_dir().join(format!("cardaminal_test_{name}"));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let wallet_db = WalletDB::open(name, &dir).await.unwrap();
+
+        let token = babbage::Value::Multiasset(
+            3_000_000,
+            vec![(POLICY.into(), vec![(b"token".to_vec().into(), 1)].into())].into(),
+        );
+
+        let cbors: Vec<_> = [
+            (key_address(), babbage::Value::Coin(10_000_000)),
+            (key_address(), babbage::Value::Coin(2_000_000)),
+            (key_address(), babbage::Value::Coin(250_000)),
+            (
+                address(ShelleyPaymentPart::script_hash([5; 28].into())),
+                babbage::Value::Coin(5_000_000),
+            ),
+            (key_address(), token),
+        ]
+        .into_iter()
+        .map(|(address, value)| {
+            let output =
+                babbage::TransactionOutput::PostAlonzo(babbage::PostAlonzoTransactionOutput {
+                    address: address.to_vec().into(),
+                    value,
+                    datum_option: None,
+                    script_ref: None,
+                });
+
+            minicbor::to_vec(output).unwrap()
+        })
+        .collect();
+
+        let utxos = cbors
+            .iter()
+            .enumerate()
+            .map(|(index, cbor)| {
+                let output = MultiEraOutput::decode(Era::Babbage, cbor).unwrap();
+                (UTXO_HASH, index, output, 100, Era::Babbage)
+            })
+            .collect();
+
+        wallet_db.insert_utxos(utxos).await.unwrap();
+
+        (wallet_db, dir)
+    }
+
+    /// Transaction spending the 10 ada utxo and minting with a Plutus policy
+    fn mint(fee: u64) -> StagingTransaction {
+        StagingTransaction::new()
+            .input(Input::new(UTXO_HASH.into(), 0))
+            .output(Output::new(key_address(), 10_000_000 - fee))
+            .mint_asset(POLICY.into(), b"other".to_vec(), 1)
+            .unwrap()
+            .add_mint_redeemer(
+                POLICY.into(),
+                vec![0x80],
+                Some(staging::ex_units(1_000, 100_000)),
+            )
+            .change_address(key_address())
+            .fee(fee)
+    }
+
+    async fn prepare(
+        tx: StagingTransaction,
+        wallet_db: &WalletDB,
+        pparams: &ProtocolParams,
+    ) -> miette::Result<StagingTx> {
+        let mut staging: StagingTx =
+            serde_json::from_value(serde_json::to_value(tx).unwrap()).unwrap();
+
+        prepare_collateral(&mut staging, &wallet(), wallet_db, pparams, Path::new("")).await?;
+
+        Ok(staging)
+    }
+
+    fn collateral_indexes(staging: &StagingTx) -> Vec<u64> {
+        staging
+            .tx
+            .collateral_inputs
+            .iter()
+            .flatten()
+            .map(|x| x.txo_index)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn automatic_collateral() {
+        let (wallet_db, dir) = wallet_db("build_automatic_collateral").await;
+        let mut pparams = ProtocolParams::default();
+
+        // largest key locked utxo holding only lovelace, the excess returned
+        let staging = prepare(mint(200_000), &wallet_db, &pparams).await.unwrap();
+
+        assert_eq!(collateral_indexes(&staging), vec![1]);
+        assert_eq!(staging.total_collateral, Some(300_000));
+        assert_eq!(
+            staging.tx.collateral_output.as_ref().map(|x| x.lovelace),
+            Some(1_700_000)
+        );
+
+        // a larger fee needs both lovelace only utxos, their 150000 lovelace
+        // of excess are too little for a collateral return
+        let staging = prepare(mint(1_400_000), &wallet_db, &pparams)
+            .await
+            .unwrap();
+        assert_eq!(collateral_indexes(&staging), vec![1, 2]);
+        assert!(staging.tx.collateral_output.is_none());
+        assert_eq!(staging.total_collateral, Some(2_250_000));
+
+        pparams.max_collateral_inputs = 1;
+        let err = prepare(mint(1_400_000), &wallet_db, &pparams)
+            .await
+            .map(|_| ())
+            .unwrap_err();
+        assert!(err.to_string().contains("over the maximum of 1"));
+
+        let err = prepare(mint(2_000_000), &wallet_db, &pparams)
+            .await
+            .map(|_| ())
+            .unwrap_err();
+        assert!(err.to_string().starts_with("not enough lovelace"));
+
+        // transactions without scripts need no collateral
+        let tx = StagingTransaction::new()
+            .input(Input::new(UTXO_HASH.into(), 0))
+            .fee(200_000);
+        let staging = prepare(tx, &wallet_db, &pparams).await.unwrap();
+        assert!(staging.tx.collateral_inputs.is_none());
+        assert!(staging.total_collateral.is_none());
+
+        drop(wallet_db);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn manual_collateral() {
+        let (wallet_db, dir) = wallet_db("build_manual_collateral").await;
+        let pparams = ProtocolParams::default();
+
+        // an excess too small for an output stays in the collateral
+        let tx = mint(150_000).collateral_input(Input::new(UTXO_HASH.into(), 2));
+        let staging = prepare(tx, &wallet_db, &pparams).await.unwrap();
+
+        assert_eq!(collateral_indexes(&staging), vec![2]);
+        assert!(staging.tx.collateral_output.is_none());
+        assert_eq!(staging.total_collateral, Some(250_000));
+
+        let tx = mint(150_000).collateral_input(Input::new(UTXO_HASH.into(), 4));
+        let err = prepare(tx, &wallet_db, &pparams)
+            .await
+            .map(|_| ())
+            .unwrap_err();
+        assert!(err.to_string().contains("hold native assets"));
+
+        // a collateral return set explicitly is kept as is
+        let tx = mint(150_000)
+            .collateral_input(Input::new(UTXO_HASH.into(), 4))
+            .collateral_output(Output::new(key_address(), 1_000_000));
+        let staging = prepare(tx, &wallet_db, &pparams).await.unwrap();
+
+        assert_eq!(collateral_indexes(&staging), vec![4]);
+        assert!(staging.total_collateral.is_none());
+
+        drop(wallet_db);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,5 +1,6 @@
 use comfy_table::Table;
 use serde::Serialize;
+use serde_json::Value as JsonValue;
 
 use crate::utils::OutputFormatter;
 use crate::wallet::dal::entities::transaction::Model as TransactionModel;
@@ -43,3 +44,146 @@ impl From<TransactionModel> for TransactionView {
         }
     }
 }
+
+/// Transaction shown by `transaction inspect`: the staging or built
+/// transaction as stored, with the details derived from its body
+#[derive(Debug, Serialize)]
+pub struct InspectView {
+    #[serde(flatten)]
+    pub transaction: JsonValue,
+    #[serde(skip_serializing_if = "InspectDetails::is_empty")]
+    pub details: InspectDetails,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct InspectDetails {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub redeemers: Vec<RedeemerView>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub datums: Vec<DatumView>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub signers: Vec<SignerView>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub native_scripts: Vec<NativeScriptView>,
+}
+
+impl InspectDetails {
+    pub fn is_empty(&self) -> bool {
+        self.redeemers.is_empty()
+            && self.datums.is_empty()
+            && self.signers.is_empty()
+            && self.native_scripts.is_empty()
+    }
+}
+
+/// Redeemer with what it is for, its data in the detailed JSON schema and
+/// its execution budget
+#[derive(Debug, Serialize)]
+pub struct RedeemerView {
+    pub redeemer: String,
+    pub purpose: String,
+    pub data: JsonValue,
+    pub mem: u32,
+    pub steps: u64,
+}
+
+/// Datum of the witness set or inline datum of an output
+#[derive(Debug, Serialize)]
+pub struct DatumView {
+    pub source: String,
+    pub hash: String,
+    pub data: JsonValue,
+}
+
+/// Key which has to sign the transaction, or which signed it without being
+/// required to
+#[derive(Debug, Serialize)]
+pub struct SignerView {
+    pub key_hash: String,
+    pub required_by: Vec<String>,
+    pub signed: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NativeScriptView {
+    pub hash: String,
+    pub satisfied: bool,
+}
+
+impl OutputFormatter for InspectView {
+    fn to_table(&self) {
+        let json = serde_json::to_string_pretty(&self.transaction).unwrap();
+        println!("{json}");
+
+        if !self.details.redeemers.is_empty() {
+            let mut table = Table::new();
+            table.set_header(vec!["redeemer", "purpose", "data", "mem", "steps"]);
+
+            for redeemer in self.details.redeemers.iter() {
+                table.add_row(vec![
+                    redeemer.redeemer.clone(),
+                    redeemer.purpose.clone(),
+                    redeemer.data.to_string(),
+                    redeemer.mem.to_string(),
+                    redeemer.steps.to_string(),
+                ]);
+            }
+
+            println!("{table}");
+        }
+
+        if !self.details.datums.is_empty() {
+            let mut table = Table::new();
+            table.set_header(vec!["datum", "hash", "data"]);
+
+            for datum in self.details.datums.iter() {
+                table.add_row(vec![
+                    datum.source.clone(),
+                    datum.hash.clone(),
+                    datum.data.to_string(),
+                ]);
+            }
+
+            println!("{table}");
+        }
+
+        if !self.details.signers.is_empty() {
+            let mut table = Table::new();
+            table.set_header(vec!["signer", "required by", "signed"]);
+
+            for signer in self.details.signers.iter() {
+                table.add_row(vec![
+                    signer.key_hash.clone(),
+                    signer.required_by.join("\n"),
+                    if signer.signed { "yes" } else { "no" }.into(),
+                ]);
+            }
+
+            println!("{table}");
+        }
+
+        if !self.details.native_scripts.is_empty() {
+            let mut table = Table::new();
+            table.set_header(vec!["native script", "signatures"]);
+
+            for script in self.details.native_scripts.iter() {
+                table.add_row(vec![
+                    script.hash.clone(),
+                    if script.satisfied {
+                        "enough"
+                    } else {
+                        "missing"
+                    }
+                    .into(),
+                ]);
+            }
+
+            println!("{table}");
+        }
+    }
+
+    fn to_json(&self) {
+        let json = serde_json::to_string_pretty(self).unwrap();
+        println!("{json}");
+    }
+}
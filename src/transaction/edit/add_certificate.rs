@@ -0,0 +1,104 @@
+use bech32::FromBase32;
+use clap::{Parser, ValueEnum};
+use miette::{bail, miette, Context, IntoDiagnostic};
+use pallas::crypto::hash::Hash;
+use tracing::instrument;
+
+use crate::{transaction::staging::Certificate, wallet::config::Wallet};
+
+use super::common::with_staging;
+
+#[derive(Parser)]
+pub struct Args {
+    /// kind of certificate
+    kind: Kind,
+
+    /// hex stake key hash the certificate refers to, defaults to the stake
+    /// key of the wallet
+    #[arg(long)]
+    stake_key_hash: Option<String>,
+
+    /// pool to delegate to, either a bech32 pool id or its hex key hash
+    /// (only for stake-delegation)
+    #[arg(long)]
+    pool: Option<String>,
+}
+
+// named after the ledger certificates
+#[allow(clippy::enum_variant_names)]
+#[derive(ValueEnum, Clone)]
+enum Kind {
+    StakeRegistration,
+    StakeDeregistration,
+    StakeDelegation,
+}
+
+#[instrument("add certificate", skip_all, fields(args))]
+pub async fn run(args: Args, ctx: &super::EditContext<'_>) -> miette::Result<()> {
+    let stake_key_hash = match args.stake_key_hash {
+        Some(x) => x,
+        None => {
+            let wallet = Wallet::load_config(&ctx.global_ctx.dirs.root_dir, &ctx.wallet)?
+                .ok_or(miette!("wallet doesn't exist"))?;
+
+            wallet
+                .keys
+                .stake_key_hash
+                .ok_or(miette!("wallet has no stake key, use --stake-key-hash"))?
+        }
+    };
+
+    let stake_key_hash = parse_hash(&stake_key_hash).context("parsing stake key hash")?;
+
+    let certificate = match args.kind {
+        Kind::StakeRegistration => Certificate::StakeRegistration { stake_key_hash },
+        Kind::StakeDeregistration => Certificate::StakeDeregistration { stake_key_hash },
+        Kind::StakeDelegation => {
+            let pool = args
+                .pool
+                .ok_or(miette!("--pool is required for stake delegation"))?;
+
+            Certificate::StakeDelegation {
+                stake_key_hash,
+                pool_id: parse_pool_id(&pool)?,
+            }
+        }
+    };
+
+    with_staging(ctx, move |mut staging| {
+        if staging.certificates.contains(&certificate) {
+            bail!("certificate already present in the transaction")
+        }
+
+        staging.certificates.push(certificate);
+
+        Ok(staging)
+    })
+    .await
+}
+
+fn parse_hash(value: &str) -> miette::Result<Hash<28>> {
+    let bytes: [u8; 28] = hex::decode(value)
+        .into_diagnostic()?
+        .try_into()
+        .map_err(|_| miette!("hash incorrect length"))?;
+
+    Ok(bytes.into())
+}
+
+fn parse_pool_id(value: &str) -> miette::Result<Hash<28>> {
+    if let Ok((hrp, data, _)) = bech32::decode(value) {
+        if hrp != "pool" {
+            bail!("pool id must use the 'pool' bech32 prefix");
+        }
+
+        let bytes: [u8; 28] = Vec::<u8>::from_base32(&data)
+            .into_diagnostic()?
+            .try_into()
+            .map_err(|_| miette!("pool id incorrect length"))?;
+
+        return Ok(bytes.into());
+    }
+
+    parse_hash(value).context("parsing pool id")
+}
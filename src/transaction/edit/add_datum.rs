@@ -2,9 +2,11 @@ use std::{fs, path::PathBuf};
 
 use clap::Parser;
 use miette::{bail, Context, IntoDiagnostic};
+use pallas::{codec::minicbor, crypto::hash::Hasher, ledger::primitives::alonzo::PlutusData};
 use tracing::instrument;
 
 use super::common::with_staging_tx;
+use crate::transaction::plutus_data;
 
 #[derive(Parser)]
 pub struct Args {
@@ -14,6 +16,9 @@ pub struct Args {
     /// file path datum bytes
     #[arg(long, action)]
     file: Option<PathBuf>,
+    /// datum as JSON in the cardano-cli detailed schema
+    #[arg(long, action, conflicts_with_all = ["hex", "file"])]
+    datum_json: Option<String>,
 }
 
 #[instrument("add datum", skip_all, fields(args))]
@@ -27,9 +32,21 @@ pub async fn run(args: Args, ctx: &super::EditContext<'_>) -> miette::Result<()>
             bail!("datum file path doesn't exist")
         }
         fs::read(path).into_diagnostic()?
+    } else if let Some(json) = args.datum_json {
+        plutus_data::cbor_from_json_str(&json)?
     } else {
-        bail!("hex or file path is required");
+        bail!("hex, file path or json is required");
     };
 
-    with_staging_tx(ctx, move |tx| Ok(tx.datum(datum_bytes))).await
+    let _: PlutusData = minicbor::decode(&datum_bytes)
+        .into_diagnostic()
+        .context("datum is not valid plutus data cbor")?;
+
+    let hash = Hasher::<256>::hash(&datum_bytes);
+
+    with_staging_tx(ctx, move |tx| Ok(tx.datum(datum_bytes))).await?;
+
+    println!("{hash}");
+
+    Ok(())
 }
@@ -1,11 +1,15 @@
 use std::{fs, path::PathBuf};
 
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use miette::{bail, Context, IntoDiagnostic};
-use pallas::txbuilder::Output;
-use tracing::instrument;
-
-use crate::transaction::edit::common::with_staging_tx;
+use pallas::{crypto::hash::Hasher, txbuilder::Output};
+use tracing::{info, instrument, warn};
+
+use super::add_script::Kind;
+use crate::transaction::{
+    edit::common::{protocol_params, with_staging_tx},
+    plutus_data,
+};
 use pallas::ledger::addresses::Address;
 
 #[derive(Parser)]
@@ -28,13 +32,37 @@ pub struct Args {
     #[arg(long, action)]
     datum_file: Option<PathBuf>,
 
-    /// reference script hash
+    /// datum as JSON in the cardano-cli detailed schema
+    #[arg(long, action, conflicts_with_all = ["datum", "datum_file"])]
+    datum_json: Option<String>,
+
+    /// how the output carries the datum: inline, only its hash, or its hash
+    /// with the datum added to the witness set
+    #[arg(long, default_value = "inline")]
+    datum_kind: DatumKind,
+
+    /// reference script via hex string
     #[arg(long, action)]
     reference_script: Option<String>,
 
-    /// reference script file path
+    /// reference script via file path
     #[arg(long, action)]
     reference_script_file: Option<PathBuf>,
+
+    /// type of the reference script
+    #[arg(long, default_value = "plutus-v2")]
+    reference_script_kind: Kind,
+
+    /// raise the lovelace amount to the minimum the output has to hold
+    #[arg(long, action)]
+    auto_min_ada: bool,
+}
+
+#[derive(ValueEnum, Clone)]
+enum DatumKind {
+    Inline,
+    Hash,
+    Embed,
 }
 
 #[instrument("add", skip_all, fields())]
@@ -74,36 +102,88 @@ pub async fn run(args: Args, ctx: &super::EditContext<'_>) -> miette::Result<()>
         }
     }
 
-    /*
-       TODO accept datum hash OR inline datum. right now we will assume inline
-
-       --datum <"inline" | "hash"> <BYTES>, OR
-       --datum-file <"inline" | "hash"> <FILE>
-    */
-
-    match (args.datum, args.datum_file) {
+    let datum = match (args.datum, args.datum_file) {
         (Some(_), Some(_)) => bail!("datum must be specified by hex string OR by file, not both"),
         (Some(d), _) => {
             let data = hex::decode(d)
                 .into_diagnostic()
                 .context("parsing datum hex to bytes")?;
 
-            output = output.set_inline_datum(data);
+            Some(data)
         }
         (_, Some(path)) => {
             if !path.exists() {
                 bail!("datum file path doesn't exist")
             }
-            let data = fs::read(path).into_diagnostic()?;
 
-            output = output.set_inline_datum(data);
+            Some(fs::read(path).into_diagnostic()?)
         }
-        _ => (),
+        _ => args
+            .datum_json
+            .map(|x| plutus_data::cbor_from_json_str(&x))
+            .transpose()?,
     };
 
-    if args.reference_script.is_some() || args.reference_script_file.is_some() {
-        todo!("reference scripts not yet supported") // TODO
+    // datum added to the witness set along with the output
+    let mut embedded = None;
+
+    if let Some(data) = datum {
+        match args.datum_kind {
+            DatumKind::Inline => output = output.set_inline_datum(data),
+            DatumKind::Hash => output = output.set_datum_hash(Hasher::<256>::hash(&data)),
+            DatumKind::Embed => {
+                output = output.set_datum_hash(Hasher::<256>::hash(&data));
+                embedded = Some(data);
+            }
+        }
     }
 
-    with_staging_tx(ctx, move |tx| Ok(tx.output(output))).await
+    let script = match (args.reference_script, args.reference_script_file) {
+        (Some(_), Some(_)) => {
+            bail!("reference script must be specified by hex string OR by file, not both")
+        }
+        (Some(s), _) => Some(
+            hex::decode(s)
+                .into_diagnostic()
+                .context("parsing reference script hex to bytes")?,
+        ),
+        (_, Some(path)) => {
+            if !path.exists() {
+                bail!("reference script file path doesn't exist")
+            }
+
+            Some(fs::read(path).into_diagnostic()?)
+        }
+        _ => None,
+    };
+
+    if let Some(bytes) = script {
+        output = output.set_inline_script(args.reference_script_kind.into(), bytes);
+    }
+
+    // reference scripts and datums make the output bigger, and so does the
+    // lovelace it has to hold
+    let min = protocol_params(ctx).await?.min_lovelace(&output)?;
+
+    if lovelace < min {
+        if args.auto_min_ada {
+            info!(min, "output raised to the minimum lovelace");
+            output.lovelace = min;
+        } else {
+            warn!(
+                min,
+                lovelace, "output holds less than the minimum lovelace, building will fail"
+            );
+        }
+    }
+
+    with_staging_tx(ctx, move |tx| {
+        let tx = tx.output(output);
+
+        match embedded {
+            Some(data) => Ok(tx.datum(data)),
+            None => Ok(tx),
+        }
+    })
+    .await
 }
@@ -5,6 +5,7 @@ use miette::{bail, miette, Context, IntoDiagnostic};
 use tracing::instrument;
 
 use super::common::with_staging_tx;
+use crate::transaction::{plutus_data, staging};
 
 #[derive(Parser)]
 pub struct Args {
@@ -17,7 +18,17 @@ pub struct Args {
     /// file path redeemer datum bytes
     #[arg(long, action)]
     data_file: Option<PathBuf>,
-    // TODO: exunit budget
+    /// redeemer datum as JSON in the cardano-cli detailed schema
+    #[arg(long, action, conflicts_with_all = ["data_hex", "data_file"])]
+    redeemer_json: Option<String>,
+
+    /// memory budget of the redeemer, replaced by the evaluated budget when
+    /// building unless scripts evaluation is disabled
+    #[arg(long, requires = "steps")]
+    mem: Option<u32>,
+    /// cpu steps budget of the redeemer
+    #[arg(long, requires = "mem")]
+    steps: Option<u64>,
 }
 
 #[instrument("add redeemer mint", skip_all, fields(args))]
@@ -37,12 +48,19 @@ pub async fn run(args: Args, ctx: &super::EditContext<'_>) -> miette::Result<()>
             bail!("redeemer data file path not exist")
         }
         fs::read(path).into_diagnostic()?
+    } else if let Some(json) = args.redeemer_json {
+        plutus_data::cbor_from_json_str(&json)?
     } else {
-        bail!("hex or file path is required");
+        bail!("hex, file path or json is required");
     };
 
+    let ex_units = args
+        .mem
+        .zip(args.steps)
+        .map(|(mem, steps)| staging::ex_units(mem, steps));
+
     with_staging_tx(ctx, move |tx| {
-        Ok(tx.add_mint_redeemer(policy.into(), redeemer_data_bytes, None))
+        Ok(tx.add_mint_redeemer(policy.into(), redeemer_data_bytes, ex_units))
     })
     .await
 }
@@ -39,7 +39,7 @@ pub async fn run(args: Args, ctx: &super::EditContext<'_>) -> miette::Result<()>
 }
 
 #[derive(ValueEnum, Clone)]
-enum Kind {
+pub(super) enum Kind {
     Native,
     PlutusV1,
     PlutusV2,
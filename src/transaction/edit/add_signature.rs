@@ -1,11 +1,14 @@
 use clap::Parser;
 use miette::{bail, miette, Context, IntoDiagnostic};
-use pallas::txbuilder::BuiltTransaction;
+use pallas::{codec::minicbor, ledger::primitives::babbage, txbuilder::BuiltTransaction};
 use tracing::{info, instrument};
 
-use crate::wallet::{
-    config::Wallet,
-    dal::{entities::transaction::Status, WalletDB},
+use crate::{
+    transaction::witness::{add_verified_signature, is_fully_signed},
+    wallet::{
+        config::Wallet,
+        dal::{entities::transaction::Status, WalletDB},
+    },
 };
 
 #[derive(Parser)]
@@ -54,13 +57,23 @@ pub async fn run(args: Args, ctx: &super::EditContext<'_>) -> miette::Result<()>
     let mut built_tx: BuiltTransaction =
         serde_json::from_slice(&record.tx_json).into_diagnostic()?;
 
-    built_tx = built_tx
-        .add_signature(public_key.into(), signature)
-        .into_diagnostic()?;
+    built_tx = add_verified_signature(built_tx, public_key, signature)?;
+
+    let tx: babbage::Tx = minicbor::decode(&built_tx.tx_bytes.0)
+        .into_diagnostic()
+        .context("decoding built transaction")?;
+
+    // the transaction stays built until every required signature is there
+
+    let complete = is_fully_signed(&tx, &wallet_db).await?;
 
     // update db
 
-    record.status = Status::Signed;
+    record.status = if complete {
+        Status::Signed
+    } else {
+        Status::Built
+    };
     record.tx_json = serde_json::to_vec(&built_tx).into_diagnostic()?;
     record.tx_cbor = Some(built_tx.tx_bytes.0);
 
@@ -0,0 +1,34 @@
+use clap::Parser;
+use tracing::instrument;
+
+use crate::transaction::staging::{parse_reward_account, Withdrawal};
+
+use super::common::with_staging;
+
+#[derive(Parser)]
+pub struct Args {
+    /// reward account to withdraw from, as bech32 stake address or hex bytes
+    reward_account: String,
+    /// amount of lovelace to withdraw, must match the full reward balance
+    amount: u64,
+}
+
+#[instrument("add withdrawal", skip_all, fields(args))]
+pub async fn run(args: Args, ctx: &super::EditContext<'_>) -> miette::Result<()> {
+    let reward_account = hex::encode(parse_reward_account(&args.reward_account)?);
+
+    with_staging(ctx, move |mut staging| {
+        // a reward account can only be withdrawn from once per transaction
+        staging
+            .withdrawals
+            .retain(|x| x.reward_account != reward_account);
+
+        staging.withdrawals.push(Withdrawal {
+            reward_account,
+            amount: args.amount,
+        });
+
+        Ok(staging)
+    })
+    .await
+}
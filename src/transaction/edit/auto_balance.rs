@@ -0,0 +1,495 @@
+use std::{cmp::Reverse, path::Path};
+
+use clap::{Parser, ValueEnum};
+use miette::{bail, miette, Context, IntoDiagnostic};
+use pallas::{
+    ledger::addresses::{Address, ShelleyPaymentPart},
+    txbuilder::Input,
+};
+use rand::{seq::SliceRandom, thread_rng};
+use sea_orm::Order;
+use tracing::{info, instrument};
+
+use crate::{
+    chain::config::Chain,
+    transaction::{
+        build, fee,
+        pparams::ProtocolParams,
+        staging::{self, StagingTx},
+        validate,
+        value::Value,
+    },
+    wallet::{
+        config::{Addresses, Wallet},
+        dal::{entities::transaction::Status, WalletDB},
+        discovery, hd, multisig,
+    },
+};
+
+#[derive(Parser)]
+pub struct Args {
+    /// coin selection strategy used to pick inputs from the wallet
+    #[arg(short, long, default_value = "largest-first")]
+    strategy: Strategy,
+}
+
+#[derive(ValueEnum, Clone)]
+enum Strategy {
+    LargestFirst,
+    RandomImprove,
+}
+
+struct Candidate {
+    input: Input,
+    value: Value,
+}
+
+#[instrument("auto balance", skip_all, fields())]
+pub async fn run(args: Args, ctx: &super::EditContext<'_>) -> miette::Result<()> {
+    let root_dir = &ctx.global_ctx.dirs.root_dir;
+
+    let wallet = Wallet::load_config(root_dir, &ctx.wallet)?
+        .ok_or(miette::miette!("wallet doesn't exist"))?;
+
+    let wallet_db = WalletDB::open(&wallet.name, &Wallet::dir(root_dir, &wallet.name))
+        .await
+        .into_diagnostic()?;
+
+    let mut record = wallet_db
+        .fetch_by_id(&(ctx.tx_id as i32))
+        .await
+        .into_diagnostic()?
+        .ok_or(miette::miette!("transaction doesn't exist"))?;
+
+    if record.status != Status::Staging {
+        bail!("can only balance transactions in staging state")
+    }
+
+    let mut staging: StagingTx = serde_json::from_slice(&record.tx_json).into_diagnostic()?;
+
+    let pparams = ProtocolParams::load(&wallet_db).await?;
+    let cost_models = build::plutus_cost_models(&staging, &wallet_db, &pparams).await?;
+
+    let change_address = match &staging.tx.change_address {
+        Some(address) => address.0.clone(),
+        None => wallet_change_address(&wallet, &wallet_db, root_dir).await?,
+    };
+
+    // the change output of an earlier balancing is rebalanced into instead
+    // of adding a second one next to it
+    if let Some(index) = staging.change_output.take() {
+        if index >= staging.tx.outputs.as_ref().map_or(0, Vec::len) {
+            bail!("change output of the earlier balancing no longer exists");
+        }
+
+        staging.tx = std::mem::take(&mut staging.tx).remove_output(index);
+    }
+
+    let tx = &staging.tx;
+
+    // a fee left by an earlier balancing is recomputed, any other fee was set
+    // explicitly by the user and is respected as is
+    let fixed_fee = tx.fee.is_some() && tx.fee != staging.balanced_fee;
+
+    // value which still has to be provided by new inputs, excluding the fee
+
+    let mut required = Value::default();
+
+    for output in tx.outputs.iter().flatten() {
+        required.add(&Value::from_staging_output(output));
+    }
+
+    required.sub(&Value::from_mint(tx));
+    required.lovelace -= staging.implicit_lovelace(pparams.key_deposit);
+
+    for input in tx.inputs.iter().flatten() {
+        let resolved = wallet_db
+            .resolve_utxo(&input.tx_hash.0, input.txo_index as i32)
+            .await
+            .into_diagnostic()
+            .context("resolving input")?
+            .ok_or(miette!("can't find required utxo in wallet"))?;
+
+        required.sub(&validate::utxo_value(&resolved)?);
+    }
+
+    let mut unbalanced = required.clone();
+    unbalanced.lovelace += tx.fee.unwrap_or_default() as i128;
+    unbalanced.normalize();
+
+    if fixed_fee && unbalanced == Value::default() {
+        bail!("transaction is already balanced")
+    }
+
+    // available utxos not already spent by the transaction
+
+    let used: Vec<_> = tx
+        .inputs
+        .iter()
+        .flatten()
+        .chain(tx.collateral_inputs.iter().flatten())
+        .collect();
+
+    // outputs locked by the script of a multisig wallet can be spent, the
+    // script is attached automatically
+    let multisig_hash = wallet
+        .keys
+        .multisig_script()?
+        .map(|x| multisig::hash(&x).to_vec());
+
+    let mut candidates = vec![];
+
+    for utxo in wallet_db
+        .fetch_all_utxos(Order::Asc)
+        .await
+        .into_diagnostic()?
+    {
+        let hash: [u8; 32] = utxo
+            .tx_hash
+            .clone()
+            .try_into()
+            .map_err(|_| miette!("malformed utxo hash in wallet db"))?;
+
+        let input = Input::new(hash.into(), utxo.txo_index as u64);
+
+        // other script locked utxos need a script witness, they can't be
+        // selected automatically
+        let unspendable = utxo.is_script() && multisig_hash.as_ref() != Some(&utxo.payment_cred);
+
+        if used.contains(&&input) || unspendable {
+            continue;
+        }
+
+        candidates.push(Candidate {
+            value: validate::utxo_value(&utxo)?,
+            input,
+        });
+    }
+
+    // select inputs until the selection covers outputs, fee and change
+
+    let mut selected: Vec<Candidate> = vec![];
+    let mut fee = if fixed_fee {
+        tx.fee.unwrap_or_default()
+    } else {
+        0
+    };
+
+    // lovelace the change output has to hold, which depends on the assets
+    // it ends up carrying
+    let mut min_change = 0;
+
+    let balanced = loop {
+        let mut target = required.clone();
+        target.lovelace += (fee + min_change) as i128;
+
+        match args.strategy {
+            Strategy::LargestFirst => select_largest_first(&mut candidates, &mut selected, &target),
+            Strategy::RandomImprove => {
+                select_random_improve(&mut candidates, &mut selected, &target)
+            }
+        }
+
+        let selected_value = total_value(&selected);
+
+        if !selected_value.covers(&target) {
+            bail!("not enough funds in wallet to balance the transaction")
+        }
+
+        let mut change = selected_value;
+        change.sub(&required);
+        change.lovelace -= fee as i128;
+        change.normalize();
+
+        let change_output = change.to_output(change_address.clone())?;
+        let needed_change = pparams.min_lovelace(&change_output)?;
+
+        if change_output.lovelace < needed_change {
+            min_change = needed_change;
+            continue;
+        }
+
+        let mut candidate = staging.clone();
+        candidate.cost_models = cost_models.clone();
+
+        for c in selected.iter() {
+            candidate.tx = candidate.tx.input(staging::copy_input(&c.input));
+        }
+
+        candidate.change_output = Some(candidate.tx.outputs.as_ref().map_or(0, Vec::len));
+        candidate.balanced_fee = (!fixed_fee).then_some(fee);
+        candidate.tx = candidate.tx.output(change_output).fee(fee);
+
+        build::attach_multisig_script(&mut candidate, &wallet, &wallet_db).await?;
+
+        if fixed_fee {
+            break candidate;
+        }
+
+        // the fee has to cover the execution of the scripts and the
+        // collateral they need, which depends on the fee itself
+        build::evaluate_scripts(&mut candidate, &wallet, &wallet_db, &pparams, root_dir).await?;
+        build::prepare_collateral(&mut candidate, &wallet, &wallet_db, &pparams, root_dir).await?;
+
+        let needed = fee::estimate_fee(&candidate, &wallet_db, &pparams).await?;
+
+        if needed <= fee {
+            break candidate;
+        }
+
+        fee = needed;
+    };
+
+    info!(inputs = selected.len(), fee, "transaction balanced");
+
+    record.tx_json = serde_json::to_vec(&balanced).into_diagnostic()?;
+
+    wallet_db
+        .update_transaction(record)
+        .await
+        .into_diagnostic()?;
+
+    info!("transaction updated");
+
+    Ok(())
+}
+
+/// Change address of the wallet for the network of its chain: for HD wallets
+/// an internal address handed out before but still unused, or a fresh one if
+/// there is none, so balancing again doesn't use up the gap; the main wallet
+/// address otherwise
+async fn wallet_change_address(
+    wallet: &Wallet,
+    wallet_db: &WalletDB,
+    root_dir: &Path,
+) -> miette::Result<Address> {
+    let chain_name = wallet.chain.as_ref().ok_or(miette!(
+        "no change address set and wallet not attached to a chain"
+    ))?;
+
+    let chain = Chain::load_config(root_dir, chain_name)?.ok_or(miette!("chain doesn't exist"))?;
+
+    if let Some(account) = discovery::account_public_key(wallet)? {
+        discovery::ensure_gap(wallet_db, &account).await?;
+
+        let unused = wallet_db
+            .fetch_unused_issued_wallet_address(hd::ROLE_INTERNAL)
+            .await
+            .into_diagnostic()?;
+
+        let issued = match unused {
+            Some(x) => Some(x),
+            None => wallet_db
+                .issue_wallet_address(hd::ROLE_INTERNAL)
+                .await
+                .into_diagnostic()?,
+        };
+
+        if let Some(issued) = issued {
+            let hash: [u8; 28] = issued
+                .payment_cred
+                .try_into()
+                .map_err(|_| miette!("malformed payment credential in wallet db"))?;
+
+            let addresses = Addresses::new(
+                ShelleyPaymentPart::key_hash(hash.into()),
+                wallet.keys.delegation_part()?,
+            )?;
+
+            return Address::from_bech32(addresses.for_network(chain.address_network_id))
+                .into_diagnostic()
+                .context("parsing change address");
+        }
+    }
+
+    Address::from_bech32(wallet.addresses.for_network(chain.address_network_id))
+        .into_diagnostic()
+        .context("parsing wallet address")
+}
+
+fn total_value(selected: &[Candidate]) -> Value {
+    let mut total = Value::default();
+
+    for c in selected {
+        total.add(&c.value);
+    }
+
+    total
+}
+
+/// Pick the utxos holding the most of each required asset, then the ones
+/// holding the most lovelace, until the target is covered
+fn select_largest_first(
+    candidates: &mut Vec<Candidate>,
+    selected: &mut Vec<Candidate>,
+    target: &Value,
+) {
+    for ((policy, name), required) in target.assets.iter() {
+        candidates.sort_by_key(|c| Reverse(c.value.asset_amount(policy, name)));
+
+        while total_value(selected).asset_amount(policy, name) < *required
+            && candidates
+                .first()
+                .is_some_and(|c| c.value.asset_amount(policy, name) > 0)
+        {
+            selected.push(candidates.remove(0));
+        }
+    }
+
+    candidates.sort_by_key(|c| Reverse(c.value.lovelace));
+
+    while total_value(selected).lovelace < target.lovelace && !candidates.is_empty() {
+        selected.push(candidates.remove(0));
+    }
+}
+
+/// Random-improve selection as described in CIP-2: pick random utxos holding
+/// each required asset until it is covered, then keep adding random utxos
+/// while they bring the lovelace selection closer to twice the target
+/// without exceeding three times the target
+fn select_random_improve(
+    candidates: &mut Vec<Candidate>,
+    selected: &mut Vec<Candidate>,
+    target: &Value,
+) {
+    let mut rng = thread_rng();
+
+    candidates.shuffle(&mut rng);
+
+    for ((policy, name), required) in target.assets.iter() {
+        while total_value(selected).asset_amount(policy, name) < *required {
+            match candidates
+                .iter()
+                .position(|c| c.value.asset_amount(policy, name) > 0)
+            {
+                Some(i) => selected.push(candidates.remove(i)),
+                None => break,
+            }
+        }
+    }
+
+    while total_value(selected).lovelace < target.lovelace && !candidates.is_empty() {
+        selected.push(candidates.remove(0));
+    }
+
+    // improvement phase
+
+    let ideal = target.lovelace * 2;
+    let upper = target.lovelace * 3;
+
+    while let Some(c) = candidates.first() {
+        let current = total_value(selected).lovelace;
+        let next = current + c.value.lovelace;
+
+        if next > upper || (ideal - next).abs() >= (ideal - current).abs() {
+            break;
+        }
+
+        selected.push(candidates.remove(0));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLICY: [u8; 28] = [7; 28];
+
+    fn candidate(index: u64, lovelace: u64, tokens: i128) -> Candidate {
+        let mut value = Value::lovelace(lovelace);
+
+        if tokens > 0 {
+            value.add_asset(POLICY.into(), b"token".to_vec(), tokens);
+        }
+
+        Candidate {
+            input: Input::new([1; 32].into(), index),
+            value,
+        }
+    }
+
+    fn indexes(selected: &[Candidate]) -> Vec<u64> {
+        selected.iter().map(|x| x.input.txo_index).collect()
+    }
+
+    #[test]
+    fn largest_first_lovelace() {
+        let mut candidates = vec![
+            candidate(0, 2_000_000, 0),
+            candidate(1, 10_000_000, 0),
+            candidate(2, 5_000_000, 0),
+            candidate(3, 1_000_000, 0),
+        ];
+        let mut selected = vec![];
+
+        select_largest_first(&mut candidates, &mut selected, &Value::lovelace(12_000_000));
+
+        assert_eq!(indexes(&selected), vec![1, 2]);
+        assert_eq!(candidates.len(), 2);
+    }
+
+    #[test]
+    fn largest_first_assets_before_lovelace() {
+        let mut candidates = vec![
+            candidate(0, 50_000_000, 0),
+            candidate(1, 1_500_000, 3),
+            candidate(2, 1_500_000, 8),
+        ];
+        let mut selected = vec![];
+
+        let mut target = Value::lovelace(2_000_000);
+        target.add_asset(POLICY.into(), b"token".to_vec(), 10);
+
+        select_largest_first(&mut candidates, &mut selected, &target);
+
+        // both token utxos are needed, their lovelace already covers the target
+        assert_eq!(indexes(&selected), vec![2, 1]);
+        assert!(total_value(&selected).covers(&target));
+    }
+
+    #[test]
+    fn largest_first_short_of_funds() {
+        let mut candidates = vec![candidate(0, 1_000_000, 0), candidate(1, 2_000_000, 0)];
+        let mut selected = vec![];
+
+        select_largest_first(&mut candidates, &mut selected, &Value::lovelace(5_000_000));
+
+        assert_eq!(selected.len(), 2);
+        assert!(!total_value(&selected).covers(&Value::lovelace(5_000_000)));
+    }
+
+    #[test]
+    fn random_improve_bounds() {
+        let target = Value::lovelace(3_000_000);
+
+        for _ in 0..20 {
+            let mut candidates: Vec<_> = (0..20).map(|i| candidate(i, 1_000_000, 0)).collect();
+            let mut selected = vec![];
+
+            select_random_improve(&mut candidates, &mut selected, &target);
+
+            // improving moves towards twice the target without going over
+            // three times it
+            let total = total_value(&selected).lovelace;
+            assert!((3_000_000..=6_000_000).contains(&total), "{total}");
+            assert_eq!(candidates.len() + selected.len(), 20);
+        }
+    }
+
+    #[test]
+    fn random_improve_assets() {
+        let mut target = Value::lovelace(1_000_000);
+        target.add_asset(POLICY.into(), b"token".to_vec(), 1);
+
+        for _ in 0..20 {
+            let mut candidates: Vec<_> = (0..10).map(|i| candidate(i, 1_000_000, 0)).collect();
+            candidates.push(candidate(10, 1_200_000, 1));
+            let mut selected = vec![];
+
+            select_random_improve(&mut candidates, &mut selected, &target);
+
+            assert!(indexes(&selected).contains(&10));
+            assert!(total_value(&selected).covers(&target));
+        }
+    }
+}
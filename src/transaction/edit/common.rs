@@ -2,11 +2,28 @@ use miette::IntoDiagnostic;
 use pallas::txbuilder::StagingTransaction;
 use tracing::info;
 
-use crate::wallet::{config::Wallet, dal::WalletDB};
+use crate::{
+    chain::config::Chain,
+    transaction::{pparams::ProtocolParams, staging::StagingTx},
+    wallet::{config::Wallet, dal::WalletDB},
+};
 
 pub async fn with_staging_tx<F>(ctx: &super::EditContext<'_>, op: F) -> miette::Result<()>
 where
     F: FnOnce(StagingTransaction) -> miette::Result<StagingTransaction>,
+{
+    with_staging(ctx, move |mut staging| {
+        staging.tx = op(staging.tx)?;
+        Ok(staging)
+    })
+    .await
+}
+
+/// Like `with_staging_tx` but giving access to the parts of the transaction
+/// which are kept outside of the pallas staging transaction
+pub async fn with_staging<F>(ctx: &super::EditContext<'_>, op: F) -> miette::Result<()>
+where
+    F: FnOnce(StagingTx) -> miette::Result<StagingTx>,
 {
     let wallet = Wallet::load_config(&ctx.global_ctx.dirs.root_dir, &ctx.wallet)?
         .ok_or(miette::miette!("wallet doesn't exist"))?;
@@ -24,11 +41,11 @@ where
         .into_diagnostic()?
         .ok_or(miette::miette!("transaction doesn't exist"))?;
 
-    let mut tx: StagingTransaction = serde_json::from_slice(&record.tx_json).into_diagnostic()?;
+    let mut staging: StagingTx = serde_json::from_slice(&record.tx_json).into_diagnostic()?;
 
-    tx = op(tx)?;
+    staging = op(staging)?;
 
-    record.tx_json = serde_json::to_vec(&tx).into_diagnostic()?;
+    record.tx_json = serde_json::to_vec(&staging).into_diagnostic()?;
 
     wallet_db
         .update_transaction(record)
@@ -39,3 +56,42 @@ where
 
     Ok(())
 }
+
+/// Protocol parameters synced by the wallet holding the transaction
+pub async fn protocol_params(ctx: &super::EditContext<'_>) -> miette::Result<ProtocolParams> {
+    let wallet = Wallet::load_config(&ctx.global_ctx.dirs.root_dir, &ctx.wallet)?
+        .ok_or(miette::miette!("wallet doesn't exist"))?;
+
+    let wallet_db = WalletDB::open(
+        &wallet.name,
+        &Wallet::dir(&ctx.global_ctx.dirs.root_dir, &wallet.name),
+    )
+    .await
+    .into_diagnostic()?;
+
+    ProtocolParams::load(&wallet_db).await
+}
+
+/// Slot given as a number, or as a time converted using the timing of the
+/// chain the wallet is attached to
+pub fn parse_slot(ctx: &super::EditContext<'_>, value: &str) -> miette::Result<u64> {
+    if let Ok(slot) = value.parse() {
+        return Ok(slot);
+    }
+
+    let wallet = Wallet::load_config(&ctx.global_ctx.dirs.root_dir, &ctx.wallet)?
+        .ok_or(miette::miette!("wallet doesn't exist"))?;
+
+    let chain_name = wallet.chain.ok_or(miette::miette!(
+        "wallet must be attached to a chain to convert times to slots"
+    ))?;
+
+    let chain = Chain::load_config(&ctx.global_ctx.dirs.root_dir, &chain_name)?
+        .ok_or(miette::miette!("chain doesn't exist"))?;
+
+    let slot = chain.parse_slot(value)?;
+
+    info!(slot, "converted time to slot");
+
+    Ok(slot)
+}
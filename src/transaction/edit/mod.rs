@@ -1,6 +1,7 @@
 use clap::{Parser, Subcommand};
 use tracing::instrument;
 
+mod add_certificate;
 mod add_collateral_input;
 mod add_datum;
 mod add_disclosed_signer;
@@ -12,6 +13,8 @@ mod add_redeemer_spend;
 mod add_reference_input;
 mod add_script;
 mod add_signature;
+mod add_withdrawal;
+mod auto_balance;
 mod clear_change_address;
 mod clear_collateral_output;
 mod clear_fee;
@@ -19,6 +22,7 @@ mod clear_signer_amount;
 mod clear_ttl;
 mod clear_valid_hereafter;
 mod common;
+mod remove_certificate;
 mod remove_collateral_input;
 mod remove_datum;
 mod remove_disclosed_signer;
@@ -30,6 +34,7 @@ mod remove_redeemer_spend;
 mod remove_reference_input;
 mod remove_script;
 mod remove_signature;
+mod remove_withdrawal;
 mod set_change_address;
 mod set_collateral_output;
 mod set_fee;
@@ -120,6 +125,17 @@ enum Commands {
     AddSignature(add_signature::Args),
     /// remove signature from transaction
     RemoveSignature(remove_signature::Args),
+    /// adds a stake registration, deregistration or delegation certificate
+    AddCertificate(add_certificate::Args),
+    /// removes a certificate from the transaction
+    RemoveCertificate(remove_certificate::Args),
+    /// adds a reward withdrawal to the transaction
+    AddWithdrawal(add_withdrawal::Args),
+    /// removes a reward withdrawal from the transaction
+    RemoveWithdrawal(remove_withdrawal::Args),
+    /// select wallet utxos as inputs and add a change output to balance the
+    /// transaction
+    AutoBalance(auto_balance::Args),
 }
 
 pub struct EditContext<'a> {
@@ -177,5 +193,10 @@ pub async fn run(args: Args, ctx: &crate::Context) -> miette::Result<()> {
         Commands::ClearChangeAddress(args) => clear_change_address::run(args, &edit_ctx).await,
         Commands::AddSignature(args) => add_signature::run(args, &edit_ctx).await,
         Commands::RemoveSignature(args) => remove_signature::run(args, &edit_ctx).await,
+        Commands::AddCertificate(args) => add_certificate::run(args, &edit_ctx).await,
+        Commands::RemoveCertificate(args) => remove_certificate::run(args, &edit_ctx).await,
+        Commands::AddWithdrawal(args) => add_withdrawal::run(args, &edit_ctx).await,
+        Commands::RemoveWithdrawal(args) => remove_withdrawal::run(args, &edit_ctx).await,
+        Commands::AutoBalance(args) => auto_balance::run(args, &edit_ctx).await,
     }
 }
@@ -0,0 +1,25 @@
+use clap::Parser;
+use miette::bail;
+use tracing::instrument;
+
+use super::common::with_staging;
+
+#[derive(Parser)]
+pub struct Args {
+    /// certificate index
+    certificate_index: usize,
+}
+
+#[instrument("remove certificate", skip_all, fields(args))]
+pub async fn run(args: Args, ctx: &super::EditContext<'_>) -> miette::Result<()> {
+    with_staging(ctx, move |mut staging| {
+        if args.certificate_index >= staging.certificates.len() {
+            bail!("certificate index out of range")
+        }
+
+        staging.certificates.remove(args.certificate_index);
+
+        Ok(staging)
+    })
+    .await
+}
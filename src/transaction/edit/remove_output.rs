@@ -1,7 +1,7 @@
 use clap::Parser;
 use tracing::instrument;
 
-use super::common::with_staging_tx;
+use super::common::with_staging;
 
 #[derive(Parser)]
 pub struct Args {
@@ -11,5 +11,17 @@ pub struct Args {
 
 #[instrument("remove output", skip_all, fields(args))]
 pub async fn run(args: Args, ctx: &super::EditContext<'_>) -> miette::Result<()> {
-    with_staging_tx(ctx, move |tx| Ok(tx.remove_output(args.output_index))).await
+    with_staging(ctx, move |mut staging| {
+        staging.tx = staging.tx.remove_output(args.output_index);
+
+        // keep pointing at the change output of auto balancing
+        staging.change_output = match staging.change_output {
+            Some(x) if x == args.output_index => None,
+            Some(x) if x > args.output_index => Some(x - 1),
+            x => x,
+        };
+
+        Ok(staging)
+    })
+    .await
 }
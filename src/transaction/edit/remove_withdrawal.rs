@@ -0,0 +1,33 @@
+use clap::Parser;
+use miette::bail;
+use tracing::instrument;
+
+use crate::transaction::staging::parse_reward_account;
+
+use super::common::with_staging;
+
+#[derive(Parser)]
+pub struct Args {
+    /// reward account of the withdrawal, as bech32 stake address or hex bytes
+    reward_account: String,
+}
+
+#[instrument("remove withdrawal", skip_all, fields(args))]
+pub async fn run(args: Args, ctx: &super::EditContext<'_>) -> miette::Result<()> {
+    let reward_account = hex::encode(parse_reward_account(&args.reward_account)?);
+
+    with_staging(ctx, move |mut staging| {
+        let before = staging.withdrawals.len();
+
+        staging
+            .withdrawals
+            .retain(|x| x.reward_account != reward_account);
+
+        if staging.withdrawals.len() == before {
+            bail!("no withdrawal for that reward account in the transaction")
+        }
+
+        Ok(staging)
+    })
+    .await
+}
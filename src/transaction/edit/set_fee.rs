@@ -1,7 +1,7 @@
 use clap::Parser;
 use tracing::instrument;
 
-use super::common::with_staging_tx;
+use super::common::with_staging;
 
 #[derive(Parser)]
 pub struct Args {
@@ -11,5 +11,13 @@ pub struct Args {
 
 #[instrument("set_fee", skip_all, fields())]
 pub async fn run(args: Args, ctx: &super::EditContext<'_>) -> miette::Result<()> {
-    with_staging_tx(ctx, move |tx| Ok(tx.fee(args.lovelace))).await
+    with_staging(ctx, move |mut staging| {
+        staging.tx = staging.tx.fee(args.lovelace);
+
+        // the fee is the user's now, auto balancing keeps it
+        staging.balanced_fee = None;
+
+        Ok(staging)
+    })
+    .await
 }
@@ -1,15 +1,18 @@
 use clap::Parser;
 use tracing::instrument;
 
-use super::common::with_staging_tx;
+use super::common::{parse_slot, with_staging_tx};
 
 #[derive(Parser)]
 pub struct Args {
-    /// slot from which the transaction is valid
-    slot: u64,
+    /// slot from which the transaction is valid: a slot number, a timestamp
+    /// like 2024-01-31T12:00:00Z or a duration from now like +2h
+    slot: String,
 }
 
 #[instrument("set valid hereafter", skip_all, fields(args))]
 pub async fn run(args: Args, ctx: &super::EditContext<'_>) -> miette::Result<()> {
-    with_staging_tx(ctx, move |tx| Ok(tx.valid_from_slot(args.slot))).await
+    let slot = parse_slot(ctx, &args.slot)?;
+
+    with_staging_tx(ctx, move |tx| Ok(tx.valid_from_slot(slot))).await
 }
@@ -0,0 +1,213 @@
+use miette::{bail, miette, Context, IntoDiagnostic};
+use pallas::{codec::minicbor, crypto::hash::Hash, ledger::primitives::babbage, txbuilder::Input};
+use tracing::info;
+use uplc::tx::error::Error as EvalError;
+
+use crate::wallet::dal::WalletDB;
+
+use super::{
+    pparams::ProtocolParams,
+    staging::{self, StagingTx},
+};
+
+/// Fee used while evaluating, scripts only see the fee through the script
+/// context so any value of the right size does
+const FEE_PLACEHOLDER: u64 = u32::MAX as u64;
+
+/// Run the Plutus scripts of the transaction against the inputs it spends
+/// and set the execution units of every redeemer to the budget its script
+/// consumed. Fails with the script traces if any script fails.
+pub async fn evaluate(
+    staging: &mut StagingTx,
+    wallet_db: &WalletDB,
+    pparams: &ProtocolParams,
+    slot_config: (u64, u64, u32),
+) -> miette::Result<()> {
+    let mut sized = with_placeholder_budgets(staging)?;
+    sized.tx = sized.tx.fee(staging.tx.fee.unwrap_or(FEE_PLACEHOLDER));
+
+    let built = sized.build()?;
+
+    let tx: babbage::Tx = minicbor::decode(&built.tx_bytes.0)
+        .into_diagnostic()
+        .context("decoding built transaction")?;
+
+    if tx.transaction_witness_set.redeemer.is_none() {
+        return Ok(());
+    }
+
+    let utxos = resolve_utxos(&tx, wallet_db).await?;
+
+    let redeemers = uplc::tx::eval_phase_two_raw(
+        &built.tx_bytes.0,
+        &utxos,
+        &pparams.cost_models_cbor()?,
+        pparams.max_tx_ex_units,
+        slot_config,
+        false,
+        |_| (),
+    )
+    .map_err(|e| script_failure(&e))?;
+
+    for cbor in redeemers {
+        let redeemer: babbage::Redeemer = minicbor::decode(&cbor)
+            .into_diagnostic()
+            .context("decoding evaluated redeemer")?;
+
+        let data = minicbor::to_vec(&redeemer.data).into_diagnostic()?;
+
+        let ex_units = staging::ex_units(redeemer.ex_units.mem, redeemer.ex_units.steps);
+
+        info!(
+            tag = ?redeemer.tag,
+            index = redeemer.index,
+            mem = redeemer.ex_units.mem,
+            steps = redeemer.ex_units.steps,
+            "script evaluated"
+        );
+
+        let staged = std::mem::take(&mut staging.tx);
+
+        staging.tx = match redeemer_purpose(&tx.transaction_body, &redeemer)? {
+            Purpose::Spend(input) => staged.add_spend_redeemer(
+                Input::new(input.transaction_id, input.index),
+                data,
+                Some(ex_units),
+            ),
+            Purpose::Mint(policy) => staged.add_mint_redeemer(policy, data, Some(ex_units)),
+        };
+    }
+
+    Ok(())
+}
+
+/// Copy of the transaction where redeemers without execution units get an
+/// empty budget, so it can be built before its scripts are evaluated. pallas
+/// doesn't expose the redeemer purposes, so they're filled in on its serde
+/// form.
+fn with_placeholder_budgets(staging: &StagingTx) -> miette::Result<StagingTx> {
+    let mut value = serde_json::to_value(staging).into_diagnostic()?;
+
+    if let Some(redeemers) = value.get_mut("redeemers").and_then(|x| x.as_object_mut()) {
+        for redeemer in redeemers.values_mut() {
+            if redeemer.get(1).is_some_and(|x| x.is_null()) {
+                redeemer[1] = serde_json::json!({ "mem": 0, "steps": 0 });
+            }
+        }
+    }
+
+    let mut sized: StagingTx = serde_json::from_value(value).into_diagnostic()?;
+    sized.cost_models = staging.cost_models.clone();
+
+    Ok(sized)
+}
+
+/// What a redeemer is for
+pub enum Purpose {
+    Spend(babbage::TransactionInput),
+    Mint(Hash<28>),
+}
+
+impl std::fmt::Display for Purpose {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Purpose::Spend(x) => write!(f, "spend {}#{}", x.transaction_id, x.index),
+            Purpose::Mint(x) => write!(f, "mint {x}"),
+        }
+    }
+}
+
+/// Resolve the purpose of a redeemer, which points at inputs and mint
+/// policies by their index in the sorted inputs and policies of the body
+pub fn redeemer_purpose(
+    body: &babbage::TransactionBody,
+    redeemer: &babbage::Redeemer,
+) -> miette::Result<Purpose> {
+    let index = redeemer.index as usize;
+
+    match redeemer.tag {
+        babbage::RedeemerTag::Spend => {
+            let mut inputs = body.inputs.clone();
+            inputs.sort_by_key(|x| (x.transaction_id, x.index));
+
+            inputs
+                .get(index)
+                .cloned()
+                .map(Purpose::Spend)
+                .ok_or(miette!("spend redeemer points to a missing input"))
+        }
+        babbage::RedeemerTag::Mint => {
+            let mut policies: Vec<_> = body
+                .mint
+                .iter()
+                .flat_map(|x| x.iter().map(|(policy, _)| *policy))
+                .collect();
+            policies.sort();
+
+            policies
+                .get(index)
+                .copied()
+                .map(Purpose::Mint)
+                .ok_or(miette!("mint redeemer points to a missing policy"))
+        }
+        ref tag => bail!("{tag:?} redeemers are not supported"),
+    }
+}
+
+/// Inputs, reference inputs and collateral of the transaction with the
+/// outputs they spend, as CBOR pairs. All of them have to be known by the
+/// wallet.
+async fn resolve_utxos(
+    tx: &babbage::Tx,
+    wallet_db: &WalletDB,
+) -> miette::Result<Vec<(Vec<u8>, Vec<u8>)>> {
+    let body = &tx.transaction_body;
+
+    let inputs = body
+        .inputs
+        .iter()
+        .chain(body.reference_inputs.iter().flatten())
+        .chain(body.collateral.iter().flatten());
+
+    let mut utxos = vec![];
+
+    for input in inputs {
+        let resolved = wallet_db
+            .resolve_utxo(input.transaction_id.as_ref(), input.index as i32)
+            .await
+            .into_diagnostic()
+            .context("resolving input")?
+            .ok_or(miette!(
+                "can't evaluate scripts, utxo {}#{} is not known by the wallet",
+                input.transaction_id,
+                input.index
+            ))?;
+
+        utxos.push((minicbor::to_vec(input).into_diagnostic()?, resolved.cbor));
+    }
+
+    Ok(utxos)
+}
+
+/// Error for a failed evaluation, including the traces emitted by the
+/// failing script
+fn script_failure(error: &EvalError) -> miette::ErrReport {
+    fn traces(error: &EvalError) -> Vec<String> {
+        match error {
+            EvalError::RedeemerError { err, .. } => traces(err),
+            EvalError::Machine(_, _, logs) => logs.clone(),
+            _ => vec![],
+        }
+    }
+
+    let traces = traces(error);
+
+    if traces.is_empty() {
+        miette!("script evaluation failed: {error}")
+    } else {
+        miette!(
+            "script evaluation failed: {error}\ntraces:\n{}",
+            traces.join("\n")
+        )
+    }
+}
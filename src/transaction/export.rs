@@ -2,11 +2,12 @@ use std::{fs, io::Write, path::PathBuf};
 
 use clap::Parser;
 use miette::IntoDiagnostic;
-use pallas::txbuilder::StagingTransaction;
 use tracing::instrument;
 
 use crate::wallet::{config::Wallet, dal::WalletDB};
 
+use super::staging::StagingTx;
+
 #[derive(Parser)]
 pub struct Args {
     /// name of the wallet
@@ -36,7 +37,7 @@ pub async fn run(args: Args, ctx: &crate::Context) -> miette::Result<()> {
         .into_diagnostic()?
         .ok_or(miette::miette!("transaction doesn't exist"))?;
 
-    let staging_transaction: StagingTransaction =
+    let staging_transaction: StagingTx =
         serde_json::from_slice(&transaction.tx_json).into_diagnostic()?;
 
     let json = serde_json::to_vec_pretty(&staging_transaction).into_diagnostic()?;
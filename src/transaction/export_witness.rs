@@ -0,0 +1,130 @@
+use std::{fs, io::Write, path::PathBuf};
+
+use clap::{Parser, ValueEnum};
+use miette::{bail, Context, IntoDiagnostic};
+use pallas::{codec::minicbor, ledger::primitives::babbage, txbuilder::BuiltTransaction};
+use tracing::{info, instrument};
+
+use crate::wallet::{
+    config::Wallet,
+    dal::{entities::transaction::Status, WalletDB},
+};
+
+use super::{
+    sign::signing_keys,
+    witness::{VKeyWitness, WitnessFile},
+};
+
+#[derive(Parser)]
+pub struct Args {
+    /// name of the wallet holding the transaction
+    #[arg(env = "CARDAMINAL_DEFAULT_WALLET")]
+    wallet: String,
+
+    /// transaction id
+    id: i32,
+
+    /// wallet whose keys sign the transaction, defaults to the wallet holding
+    /// it
+    #[arg(long)]
+    signer: Option<String>,
+
+    /// password of the signing wallet
+    #[arg(long, short, action)]
+    password: Option<String>,
+
+    /// use interactive mode
+    #[arg(long, short, action)]
+    interactive: bool,
+
+    /// format of the witness file
+    #[arg(long, default_value = "json")]
+    format: Format,
+
+    /// output dir to save the witness file
+    #[arg(long, action)]
+    output_path: Option<PathBuf>,
+}
+
+#[derive(ValueEnum, Clone)]
+enum Format {
+    Json,
+    Cbor,
+}
+
+#[instrument("export witness", skip_all, fields(wallet=args.wallet,id=args.id))]
+pub async fn run(args: Args, ctx: &crate::Context) -> miette::Result<()> {
+    let wallet = Wallet::load_config(&ctx.dirs.root_dir, &args.wallet)?
+        .ok_or(miette::miette!("wallet doesn't exist"))?;
+
+    let wallet_db = WalletDB::open(&wallet.name, &Wallet::dir(&ctx.dirs.root_dir, &wallet.name))
+        .await
+        .into_diagnostic()?;
+
+    let record = wallet_db
+        .fetch_by_id(&args.id)
+        .await
+        .into_diagnostic()?
+        .ok_or(miette::miette!("transaction doesn't exist"))?;
+
+    if record.status == Status::Staging {
+        bail!("transaction must be built before signing");
+    }
+
+    let built_tx: BuiltTransaction = serde_json::from_slice(&record.tx_json).into_diagnostic()?;
+
+    let tx: babbage::Tx = minicbor::decode(&built_tx.tx_bytes.0)
+        .into_diagnostic()
+        .context("decoding built transaction")?;
+
+    let signer_name = args.signer.as_deref().unwrap_or(&args.wallet);
+
+    let signer = Wallet::load_config(&ctx.dirs.root_dir, signer_name)?
+        .ok_or(miette::miette!("signer wallet doesn't exist"))?;
+
+    let signer_db = WalletDB::open(&signer.name, &Wallet::dir(&ctx.dirs.root_dir, &signer.name))
+        .await
+        .into_diagnostic()?;
+
+    let password = match (args.password, args.interactive) {
+        (Some(p), _) => p,
+        (None, true) => inquire::Password::new("password:")
+            .with_help_message("the spending password of the signing wallet")
+            .with_display_mode(inquire::PasswordDisplayMode::Masked)
+            .prompt()
+            .into_diagnostic()?,
+        (None, false) => bail!("password is required"),
+    };
+
+    let mut witnesses = vec![];
+
+    for key in signing_keys(&signer, &signer_db, &tx, &password).await? {
+        let signature = key.sign(built_tx.tx_hash.0);
+        let witness = VKeyWitness::new(key.public_key().as_ref(), signature.as_ref());
+
+        witness.verify(&built_tx.tx_hash.0)?;
+        witnesses.push(witness);
+    }
+
+    let file = WitnessFile {
+        tx_hash: Some(built_tx.tx_hash.0.into()),
+        witnesses,
+    };
+
+    let (bytes, extension) = match args.format {
+        Format::Json => (serde_json::to_vec_pretty(&file).into_diagnostic()?, "json"),
+        Format::Cbor => (file.to_cbor()?, "cbor"),
+    };
+
+    let output_path = args.output_path.unwrap_or_default().join(format!(
+        "tx{}-{}.witness.{extension}",
+        record.id, signer.name
+    ));
+
+    let mut out = fs::File::create(&output_path).into_diagnostic()?;
+    out.write_all(&bytes).into_diagnostic()?;
+
+    info!(path = %output_path.display(), "witness exported");
+
+    Ok(())
+}
@@ -0,0 +1,162 @@
+use miette::{Context, IntoDiagnostic};
+use pallas::{codec::minicbor, ledger::primitives::babbage};
+
+use crate::wallet::dal::WalletDB;
+
+use super::{pparams::ProtocolParams, staging::StagingTx, witness};
+
+/// Fee used while sizing the transaction, large enough to be encoded with
+/// the same amount of bytes as any realistic fee
+const FEE_PLACEHOLDER: u64 = u32::MAX as u64;
+
+/// Encoded size of a single vkey witness: array header, 32 byte public key
+/// and 64 byte signature, each with their bytes header
+const VKEY_WITNESS_SIZE: u64 = 1 + 34 + 66;
+
+/// Witness set map key and vkey witness array header
+const VKEY_WITNESSES_OVERHEAD: u64 = 4;
+
+/// Linear fee for a transaction of the given size in bytes
+pub fn min_fee(pparams: &ProtocolParams, tx_size: u64) -> u64 {
+    pparams.min_fee_a * tx_size + pparams.min_fee_b
+}
+
+/// Estimate the amount of vkey witnesses the transaction will carry, either
+/// from the signer amount override or from the keys required to sign it.
+/// Which keys will sign for a native script isn't known in advance, so all of
+/// them are assumed to.
+pub async fn estimate_signers(
+    staging: &StagingTx,
+    tx: &babbage::Tx,
+    wallet_db: &WalletDB,
+) -> miette::Result<u64> {
+    if let Some(amount) = staging.tx.signature_amount_override {
+        return Ok(amount.into());
+    }
+
+    let signers = witness::required_signers(tx, wallet_db).await?;
+
+    Ok(signers.len().max(1) as u64)
+}
+
+/// Estimate the size of the transaction once all the expected signatures are
+/// attached
+pub async fn estimate_tx_size(staging: &StagingTx, wallet_db: &WalletDB) -> miette::Result<u64> {
+    let mut sized = staging.clone();
+    sized.tx = sized.tx.fee(FEE_PLACEHOLDER);
+
+    let built = sized.build()?;
+
+    let tx: babbage::Tx = minicbor::decode(&built.tx_bytes.0)
+        .into_diagnostic()
+        .context("decoding built transaction")?;
+
+    let signers = estimate_signers(staging, &tx, wallet_db).await?;
+
+    let body_size = built.tx_bytes.0.len() as u64;
+
+    Ok(body_size + vkey_witnesses_size(signers, false))
+}
+
+/// Size added to a transaction by attaching the given amount of vkey
+/// witnesses, including the witness set entry when it has none yet
+pub fn vkey_witnesses_size(signers: u64, has_witnesses: bool) -> u64 {
+    let overhead = if has_witnesses {
+        0
+    } else {
+        VKEY_WITNESSES_OVERHEAD
+    };
+
+    overhead + signers * VKEY_WITNESS_SIZE
+}
+
+/// Total execution units of the redeemers of the transaction, as memory and
+/// steps
+pub fn total_ex_units(staging: &StagingTx) -> miette::Result<(u64, u64)> {
+    let mut sized = staging.clone();
+    sized.tx = sized.tx.fee(FEE_PLACEHOLDER);
+
+    let built = sized.build()?;
+
+    let tx: babbage::Tx = minicbor::decode(&built.tx_bytes.0)
+        .into_diagnostic()
+        .context("decoding built transaction")?;
+
+    let total = tx
+        .transaction_witness_set
+        .redeemer
+        .iter()
+        .flatten()
+        .fold((0, 0), |(mem, steps), x| {
+            (mem + u64::from(x.ex_units.mem), steps + x.ex_units.steps)
+        });
+
+    Ok(total)
+}
+
+/// Compute the minimum fee required by the transaction, including the
+/// execution of its scripts
+pub async fn estimate_fee(
+    staging: &StagingTx,
+    wallet_db: &WalletDB,
+    pparams: &ProtocolParams,
+) -> miette::Result<u64> {
+    let size = estimate_tx_size(staging, wallet_db).await?;
+    let (mem, steps) = total_ex_units(staging)?;
+
+    Ok(min_fee(pparams, size) + pparams.script_fee(mem, steps))
+}
+
+#[cfg(test)]
+mod tests {
+    use pallas::{codec::utils::Bytes, ledger::primitives::babbage::VKeyWitness};
+
+    use super::*;
+
+    fn witness_set_size(signers: usize) -> u64 {
+        let witness_set = babbage::WitnessSet {
+            vkeywitness: (signers > 0).then(|| {
+                vec![
+                    VKeyWitness {
+                        vkey: Bytes::from(vec![0; 32]),
+                        signature: Bytes::from(vec![0; 64]),
+                    };
+                    signers
+                ]
+            }),
+            native_script: None,
+            bootstrap_witness: None,
+            plutus_v1_script: None,
+            plutus_data: None,
+            redeemer: None,
+            plutus_v2_script: None,
+        };
+
+        minicbor::to_vec(witness_set).unwrap().len() as u64
+    }
+
+    #[test]
+    fn linear_fee() {
+        let pparams = ProtocolParams::default();
+
+        assert_eq!(min_fee(&pparams, 0), 155381);
+        assert_eq!(min_fee(&pparams, 300), 44 * 300 + 155381);
+    }
+
+    #[test]
+    fn vkey_witnesses_never_underestimated() {
+        let empty = witness_set_size(0);
+
+        // the array header grows past 23 witnesses
+        for signers in 1..30 {
+            let actual = witness_set_size(signers) - empty;
+            let estimate = vkey_witnesses_size(signers as u64, false);
+
+            assert!(estimate >= actual, "{signers} signers");
+            assert!(estimate - actual <= 3, "{signers} signers");
+        }
+
+        let actual = witness_set_size(2) - witness_set_size(1);
+        assert_eq!(vkey_witnesses_size(1, true), actual);
+    }
+}
@@ -0,0 +1,115 @@
+use std::{fs, path::PathBuf};
+
+use clap::Parser;
+use miette::{bail, Context, IntoDiagnostic};
+use pallas::{codec::minicbor, ledger::primitives::babbage, txbuilder::BuiltTransaction};
+use tracing::{info, instrument};
+
+use crate::wallet::{
+    config::Wallet,
+    dal::{entities::transaction::Status, WalletDB},
+};
+
+use super::witness::{add_verified_signature, is_fully_signed, WitnessFile};
+
+#[derive(Parser)]
+pub struct Args {
+    /// name of the wallet
+    #[arg(env = "CARDAMINAL_DEFAULT_WALLET")]
+    wallet: String,
+
+    /// transaction id
+    id: i32,
+
+    /// witness files to merge into the transaction, as JSON or CBOR witness
+    /// sets
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+}
+
+#[instrument("import witness", skip_all, fields(wallet=args.wallet,id=args.id))]
+pub async fn run(args: Args, ctx: &crate::Context) -> miette::Result<()> {
+    let wallet = Wallet::load_config(&ctx.dirs.root_dir, &args.wallet)?
+        .ok_or(miette::miette!("wallet doesn't exist"))?;
+
+    let wallet_db = WalletDB::open(&wallet.name, &Wallet::dir(&ctx.dirs.root_dir, &wallet.name))
+        .await
+        .into_diagnostic()?;
+
+    let mut record = wallet_db
+        .fetch_by_id(&args.id)
+        .await
+        .into_diagnostic()?
+        .ok_or(miette::miette!("transaction doesn't exist"))?;
+
+    if record.status == Status::Staging {
+        bail!("transaction must be built before adding witnesses");
+    }
+
+    let mut built_tx: BuiltTransaction =
+        serde_json::from_slice(&record.tx_json).into_diagnostic()?;
+
+    let tx_hash = built_tx.tx_hash.0;
+
+    // every file is checked before any witness is added, so a bad file
+    // leaves the transaction untouched
+
+    let mut witnesses = vec![];
+
+    for path in args.files.iter() {
+        let file = WitnessFile::parse(&fs::read(path).into_diagnostic()?)?;
+
+        if file.tx_hash.is_some_and(|x| *x != tx_hash) {
+            bail!(
+                "witness file {} signs transaction {}, not {}",
+                path.display(),
+                file.tx_hash.unwrap(),
+                hex::encode(tx_hash)
+            );
+        }
+
+        for witness in file.witnesses {
+            witness.verify(&tx_hash)?;
+            witnesses.push(witness);
+        }
+    }
+
+    if witnesses.is_empty() {
+        bail!("witness files don't hold any signature");
+    }
+
+    for witness in witnesses.iter() {
+        built_tx =
+            add_verified_signature(built_tx, witness.vkey_bytes()?, witness.signature_bytes()?)?;
+    }
+
+    let tx: babbage::Tx = minicbor::decode(&built_tx.tx_bytes.0)
+        .into_diagnostic()
+        .context("decoding built transaction")?;
+
+    // the transaction stays built until every required signature is there
+
+    let complete = is_fully_signed(&tx, &wallet_db).await?;
+
+    // update db
+
+    record.status = if complete {
+        Status::Signed
+    } else {
+        Status::Built
+    };
+    record.tx_json = serde_json::to_vec(&built_tx).into_diagnostic()?;
+    record.tx_cbor = Some(built_tx.tx_bytes.0);
+
+    wallet_db
+        .update_transaction(record)
+        .await
+        .into_diagnostic()?;
+
+    info!(
+        witnesses = witnesses.len(),
+        complete, "witnesses added to transaction"
+    );
+
+    Ok(())
+}
@@ -1,11 +1,28 @@
 use clap::Parser;
-use miette::IntoDiagnostic;
-use pallas::txbuilder::{BuiltTransaction, StagingTransaction};
+use miette::{Context, IntoDiagnostic};
+use pallas::{
+    codec::minicbor,
+    crypto::hash::{Hash, Hasher},
+    ledger::primitives::babbage,
+    txbuilder::BuiltTransaction,
+};
 use tracing::instrument;
 
-use crate::wallet::{
-    config::Wallet,
-    dal::{entities::transaction::Status, WalletDB},
+use crate::{
+    utils::OutputFormatter,
+    wallet::{
+        config::Wallet,
+        dal::{entities::transaction::Status, WalletDB},
+        multisig,
+    },
+    OutputFormat,
+};
+
+use super::{
+    config::{DatumView, InspectDetails, InspectView, NativeScriptView, RedeemerView, SignerView},
+    eval, plutus_data,
+    staging::StagingTx,
+    witness,
 };
 
 #[derive(Parser)]
@@ -33,22 +50,166 @@ pub async fn run(args: Args, ctx: &crate::Context) -> miette::Result<()> {
         .into_diagnostic()?
         .ok_or(miette::miette!("transaction doesn't exist"))?;
 
-    let json = match transaction.status {
+    let (json, tx, built) = match transaction.status {
         Status::Staging => {
-            let staging_transaction: StagingTransaction =
+            let staging_transaction: StagingTx =
                 serde_json::from_slice(&transaction.tx_json).into_diagnostic()?;
 
-            serde_json::to_string_pretty(&staging_transaction).unwrap()
+            let json = serde_json::to_value(&staging_transaction).into_diagnostic()?;
+
+            // redeemer indexes and datums are only known once built, incomplete
+            // transactions which can't be built yet are shown as they are
+            let mut preview = staging_transaction;
+            let fee = preview.tx.fee.unwrap_or_default();
+            preview.tx = std::mem::take(&mut preview.tx).fee(fee);
+
+            let tx = match preview.build() {
+                Ok(built) => Some(decode_tx(&built)?),
+                Err(_) => None,
+            };
+
+            (json, tx, None)
         }
         _ => {
             let built_transaction: BuiltTransaction =
                 serde_json::from_slice(&transaction.tx_json).into_diagnostic()?;
 
-            serde_json::to_string_pretty(&built_transaction).unwrap()
+            let json = serde_json::to_value(&built_transaction).into_diagnostic()?;
+            let tx = decode_tx(&built_transaction)?;
+
+            (json, Some(tx), Some(built_transaction))
         }
     };
 
-    println!("{json}");
+    let mut details = InspectDetails::default();
+
+    if let Some(tx) = &tx {
+        details.redeemers = redeemer_details(tx)?;
+        details.datums = datum_details(tx);
+    }
+
+    if let (Some(built), Some(tx)) = (&built, &tx) {
+        (details.signers, details.native_scripts) = signer_details(built, tx, &wallet_db).await?;
+    }
+
+    let view = InspectView {
+        transaction: json,
+        details,
+    };
+
+    match ctx.output_format {
+        OutputFormat::Table => view.to_table(),
+        OutputFormat::Json => view.to_json(),
+    }
 
     Ok(())
 }
+
+fn decode_tx(built: &BuiltTransaction) -> miette::Result<babbage::Tx> {
+    minicbor::decode(&built.tx_bytes.0)
+        .into_diagnostic()
+        .context("decoding built transaction")
+}
+
+/// Redeemers of the transaction with what they are for, their data as JSON
+/// and their execution budget
+fn redeemer_details(tx: &babbage::Tx) -> miette::Result<Vec<RedeemerView>> {
+    let mut redeemers = vec![];
+
+    for redeemer in tx.transaction_witness_set.redeemer.iter().flatten() {
+        let purpose = eval::redeemer_purpose(&tx.transaction_body, redeemer)?;
+
+        redeemers.push(RedeemerView {
+            redeemer: format!("{:?} {}", redeemer.tag, redeemer.index),
+            purpose: purpose.to_string(),
+            data: plutus_data::to_json(&redeemer.data),
+            mem: redeemer.ex_units.mem,
+            steps: redeemer.ex_units.steps,
+        });
+    }
+
+    Ok(redeemers)
+}
+
+/// Datums of the witness set and inline datums of the outputs as JSON
+fn datum_details(tx: &babbage::Tx) -> Vec<DatumView> {
+    let witness_datums = tx
+        .transaction_witness_set
+        .plutus_data
+        .iter()
+        .flatten()
+        .map(|x| ("witness".to_string(), x));
+
+    let inline_datums =
+        tx.transaction_body
+            .outputs
+            .iter()
+            .enumerate()
+            .filter_map(|(i, x)| match x {
+                babbage::TransactionOutput::PostAlonzo(x) => match &x.datum_option {
+                    Some(babbage::DatumOption::Data(d)) => Some((format!("output {i}"), &d.0)),
+                    _ => None,
+                },
+                _ => None,
+            });
+
+    witness_datums
+        .chain(inline_datums)
+        .map(|(source, datum)| DatumView {
+            source,
+            hash: Hasher::<256>::hash_cbor(datum).to_string(),
+            data: plutus_data::to_json(datum),
+        })
+        .collect()
+}
+
+/// Signers required by the transaction and whether they signed, along with
+/// the status of its native scripts
+async fn signer_details(
+    built: &BuiltTransaction,
+    tx: &babbage::Tx,
+    wallet_db: &WalletDB,
+) -> miette::Result<(Vec<SignerView>, Vec<NativeScriptView>)> {
+    let signed: Vec<Hash<28>> = built
+        .signatures
+        .iter()
+        .flatten()
+        .map(|(public_key, _)| Hasher::<224>::hash(&public_key.0))
+        .collect();
+
+    let required = witness::required_signers(tx, wallet_db).await?;
+
+    let mut signers: Vec<_> = required
+        .iter()
+        .map(|(signer, sources)| SignerView {
+            key_hash: signer.to_string(),
+            required_by: sources.iter().map(|x| x.to_string()).collect(),
+            signed: signed.contains(signer),
+        })
+        .collect();
+
+    // signatures by keys the transaction doesn't know about are still valid
+    // witnesses, they are listed for completeness
+    for signer in signed.iter().filter(|x| !required.contains_key(x)) {
+        signers.push(SignerView {
+            key_hash: signer.to_string(),
+            required_by: vec![],
+            signed: true,
+        });
+    }
+
+    let validity = multisig::Validity::of(&tx.transaction_body);
+
+    let scripts = tx
+        .transaction_witness_set
+        .native_script
+        .iter()
+        .flatten()
+        .map(|script| NativeScriptView {
+            hash: multisig::hash(script).to_string(),
+            satisfied: multisig::is_satisfied(script, &signed, &validity),
+        })
+        .collect();
+
+    Ok((signers, scripts))
+}
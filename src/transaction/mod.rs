@@ -7,11 +7,21 @@ mod config;
 mod create;
 mod delete;
 mod edit;
+mod eval;
 mod export;
+mod export_witness;
+mod fee;
+mod import_witness;
 mod inspect;
 mod list;
+mod plutus_data;
+mod pparams;
 mod sign;
+mod staging;
 mod submit;
+mod validate;
+mod value;
+mod witness;
 
 #[derive(Parser)]
 pub struct Args {
@@ -38,10 +48,19 @@ enum Commands {
     Build(build::Args),
     /// sign a transaction using a Cardaminal wallet
     Sign(sign::Args),
+    /// check a built transaction against the ledger rules using the wallet
+    /// and chain state
+    Validate(validate::Args),
     /// submit a transaction to cardano node
     Submit(submit::Args),
     /// export a transaction to json file
     Export(export::Args),
+    /// sign a built transaction with a wallet and save the signatures to a
+    /// witness file instead of attaching them
+    ExportWitness(export_witness::Args),
+    /// verify the signatures of witness files produced elsewhere and attach
+    /// them to a built transaction
+    ImportWitness(import_witness::Args),
     /// show value balance between inputs and outputs
     Balance(balance::Args),
 }
@@ -65,6 +84,10 @@ pub async fn run(args: Args, ctx: &crate::Context) -> miette::Result<()> {
             build::run(args, ctx).await
         }
         Commands::Sign(args) => sign::run(args, ctx).await,
+        Commands::Validate(args) => {
+            crate::with_tracing();
+            validate::run(args, ctx).await
+        }
         Commands::Submit(args) => {
             crate::with_tracing();
             submit::run(args, ctx).await
@@ -73,6 +96,14 @@ pub async fn run(args: Args, ctx: &crate::Context) -> miette::Result<()> {
             crate::with_tracing();
             export::run(args, ctx).await
         }
+        Commands::ExportWitness(args) => {
+            crate::with_tracing();
+            export_witness::run(args, ctx).await
+        }
+        Commands::ImportWitness(args) => {
+            crate::with_tracing();
+            import_witness::run(args, ctx).await
+        }
         Commands::Balance(args) => balance::run(args, ctx).await,
     }
 }
@@ -0,0 +1,287 @@
+use miette::{bail, miette, Context, IntoDiagnostic};
+use pallas::{
+    codec::{
+        minicbor,
+        utils::{Int, KeyValuePairs},
+    },
+    ledger::primitives::alonzo::{BigInt, Constr, PlutusData},
+};
+use serde_json::{json, Value as JsonValue};
+
+/// Plutus data in the detailed JSON schema used by cardano-cli
+pub fn to_json(data: &PlutusData) -> JsonValue {
+    match data {
+        PlutusData::Constr(x) => {
+            let constructor = match x.tag {
+                121..=127 => x.tag - 121,
+                1280..=1400 => x.tag - 1280 + 7,
+                _ => x.any_constructor.unwrap_or_default(),
+            };
+
+            json!({
+                "constructor": constructor,
+                "fields": x.fields.iter().map(to_json).collect::<Vec<_>>(),
+            })
+        }
+        PlutusData::Map(x) => {
+            let entries: Vec<_> = x
+                .iter()
+                .map(|(k, v)| json!({ "k": to_json(k), "v": to_json(v) }))
+                .collect();
+
+            json!({ "map": entries })
+        }
+        PlutusData::BigInt(x) => json!({ "int": big_int_to_json(x) }),
+        PlutusData::BoundedBytes(x) => json!({ "bytes": hex::encode(x.to_vec()) }),
+        PlutusData::Array(x) => json!({ "list": x.iter().map(to_json).collect::<Vec<_>>() }),
+    }
+}
+
+/// Parse plutus data in the detailed JSON schema used by cardano-cli
+pub fn from_json(value: &JsonValue) -> miette::Result<PlutusData> {
+    let object = value
+        .as_object()
+        .ok_or(miette!("plutus data must be a JSON object, got {value}"))?;
+
+    if object.len() != 1 && !(object.len() == 2 && object.contains_key("fields")) {
+        bail!("unexpected keys in plutus data object {value}");
+    }
+
+    if let Some(constructor) = object.get("constructor") {
+        let constructor = constructor
+            .as_u64()
+            .ok_or(miette!("constructor must be an unsigned integer"))?;
+
+        let fields = match object.get("fields") {
+            Some(JsonValue::Array(x)) => x.iter().map(from_json).collect::<miette::Result<_>>()?,
+            _ => bail!("constructor {constructor} is missing its list of fields"),
+        };
+
+        // compact tags for the first constructors, the general form for
+        // the rest
+        let (tag, any_constructor) = match constructor {
+            0..=6 => (121 + constructor, None),
+            7..=127 => (1280 + constructor - 7, None),
+            _ => (102, Some(constructor)),
+        };
+
+        return Ok(PlutusData::Constr(Constr {
+            tag,
+            any_constructor,
+            fields,
+        }));
+    }
+
+    if let Some(entries) = object.get("map") {
+        let entries = entries
+            .as_array()
+            .ok_or(miette!("map must be a list of k/v objects"))?
+            .iter()
+            .map(|x| {
+                let k = x.get("k").ok_or(miette!("map entry is missing its key"))?;
+                let v = x
+                    .get("v")
+                    .ok_or(miette!("map entry is missing its value"))?;
+
+                Ok((from_json(k)?, from_json(v)?))
+            })
+            .collect::<miette::Result<Vec<_>>>()?;
+
+        return Ok(PlutusData::Map(KeyValuePairs::from(entries)));
+    }
+
+    if let Some(x) = object.get("int") {
+        return Ok(PlutusData::BigInt(big_int_from_json(x)?));
+    }
+
+    if let Some(x) = object.get("bytes") {
+        let bytes = x
+            .as_str()
+            .ok_or(miette!("bytes must be a hex string"))
+            .and_then(|x| hex::decode(x).into_diagnostic())
+            .context("parsing plutus data bytes")?;
+
+        return Ok(PlutusData::BoundedBytes(bytes.into()));
+    }
+
+    if let Some(x) = object.get("list") {
+        let items = x
+            .as_array()
+            .ok_or(miette!("list must be a JSON array"))?
+            .iter()
+            .map(from_json)
+            .collect::<miette::Result<_>>()?;
+
+        return Ok(PlutusData::Array(items));
+    }
+
+    bail!("unknown plutus data object {value}")
+}
+
+/// Encode plutus data given as detailed schema JSON text to CBOR
+pub fn cbor_from_json_str(text: &str) -> miette::Result<Vec<u8>> {
+    let value: JsonValue = serde_json::from_str(text)
+        .into_diagnostic()
+        .context("parsing plutus data json")?;
+
+    minicbor::to_vec(from_json(&value)?).into_diagnostic()
+}
+
+/// Integers can be JSON numbers or decimal strings for values which don't
+/// fit in one
+fn big_int_from_json(value: &JsonValue) -> miette::Result<BigInt> {
+    let text = match value {
+        JsonValue::Number(x) => x.to_string(),
+        JsonValue::String(x) => x.clone(),
+        _ => bail!("int must be a number or a decimal string"),
+    };
+
+    if let Ok(x) = text.parse::<i128>() {
+        if let Ok(x) = minicbor::data::Int::try_from(x) {
+            return Ok(BigInt::Int(Int(x)));
+        }
+    }
+
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(x) => (true, x),
+        None => (false, text.as_str()),
+    };
+
+    let bytes = from_decimal(digits, negative).ok_or(miette!("invalid int {text}"))?;
+
+    // negative big integers are encoded as -1 - n
+    if negative {
+        Ok(BigInt::BigNInt(bytes.into()))
+    } else {
+        Ok(BigInt::BigUInt(bytes.into()))
+    }
+}
+
+/// Integers which don't fit in a JSON number are given as decimal strings
+fn big_int_to_json(value: &BigInt) -> JsonValue {
+    match value {
+        BigInt::Int(x) => {
+            let x = i128::from(x.0);
+
+            match i64::try_from(x) {
+                Ok(small) => json!(small),
+                Err(_) => json!(x.to_string()),
+            }
+        }
+        BigInt::BigUInt(x) => json!(decimal(x, false)),
+        // negative big integers are encoded as -1 - n
+        BigInt::BigNInt(x) => json!(format!("-{}", decimal(x, true))),
+    }
+}
+
+/// Decimal representation of a big-endian unsigned integer, optionally
+/// incremented by one
+fn decimal(bytes: &[u8], plus_one: bool) -> String {
+    // little-endian base 10 digits
+    let mut digits: Vec<u32> = vec![0];
+
+    let add = |digits: &mut Vec<u32>, multiplier: u32, mut carry: u32| {
+        for d in digits.iter_mut() {
+            let v = *d * multiplier + carry;
+            *d = v % 10;
+            carry = v / 10;
+        }
+
+        while carry > 0 {
+            digits.push(carry % 10);
+            carry /= 10;
+        }
+    };
+
+    for byte in bytes {
+        add(&mut digits, 256, *byte as u32);
+    }
+
+    if plus_one {
+        add(&mut digits, 1, 1);
+    }
+
+    while digits.len() > 1 && digits.last() == Some(&0) {
+        digits.pop();
+    }
+
+    digits.iter().rev().map(|d| d.to_string()).collect()
+}
+
+/// Big-endian bytes of an unsigned decimal integer, optionally decremented
+/// by one
+fn from_decimal(text: &str, minus_one: bool) -> Option<Vec<u8>> {
+    if text.is_empty() || !text.bytes().all(|x| x.is_ascii_digit()) {
+        return None;
+    }
+
+    // little-endian base 256 digits
+    let mut bytes: Vec<u8> = vec![0];
+
+    for digit in text.bytes().map(|x| (x - b'0') as u32) {
+        let mut carry = digit;
+
+        for b in bytes.iter_mut() {
+            let v = *b as u32 * 10 + carry;
+            *b = (v % 256) as u8;
+            carry = v / 256;
+        }
+
+        while carry > 0 {
+            bytes.push((carry % 256) as u8);
+            carry /= 256;
+        }
+    }
+
+    if minus_one {
+        for b in bytes.iter_mut() {
+            if *b > 0 {
+                *b -= 1;
+                break;
+            }
+
+            *b = 255;
+        }
+    }
+
+    while bytes.len() > 1 && bytes.last() == Some(&0) {
+        bytes.pop();
+    }
+
+    bytes.reverse();
+
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn big_decimal() {
+        assert_eq!(decimal(&[], false), "0");
+        assert_eq!(decimal(&[0x01, 0x00], false), "256");
+        assert_eq!(decimal(&[0xff; 16], false), u128::MAX.to_string());
+        assert_eq!(decimal(&[0xff], true), "256");
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let value = json!({
+            "constructor": 0,
+            "fields": [
+                { "int": 42 },
+                { "int": "-340282366920938463463374607431768211456" },
+                { "int": "340282366920938463463374607431768211455" },
+                { "bytes": "deadbeef" },
+                { "list": [{ "constructor": 200, "fields": [] }] },
+                { "map": [{ "k": { "bytes": "" }, "v": { "int": -1 } }] }
+            ]
+        });
+
+        assert_eq!(to_json(&from_json(&value).unwrap()), value);
+
+        assert!(from_json(&json!({ "int": "12a" })).is_err());
+        assert!(from_json(&json!({ "constructor": 1 })).is_err());
+    }
+}
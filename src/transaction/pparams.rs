@@ -0,0 +1,337 @@
+use miette::{miette, Context, IntoDiagnostic};
+use pallas::{
+    codec::minicbor,
+    ledger::primitives::babbage::{
+        self, CostMdls, ExUnitPrices, RationalNumber, TransactionOutput, Update,
+    },
+    txbuilder::{BuildBabbage, Output, StagingTransaction},
+};
+
+use crate::wallet::dal::WalletDB;
+
+// Babbage-era values used for any parameter which hasn't been seen in an
+// on-chain update proposal yet
+const DEFAULT_MIN_FEE_A: u64 = 44;
+const DEFAULT_MIN_FEE_B: u64 = 155381;
+const DEFAULT_MAX_TX_SIZE: u64 = 16384;
+const DEFAULT_KEY_DEPOSIT: u64 = 2_000_000;
+const DEFAULT_COINS_PER_UTXO_BYTE: u64 = 4310;
+const DEFAULT_MAX_VALUE_SIZE: u64 = 5000;
+const DEFAULT_COLLATERAL_PERCENTAGE: u64 = 150;
+const DEFAULT_MAX_COLLATERAL_INPUTS: u64 = 3;
+const DEFAULT_PRICE_MEM: (u64, u64) = (577, 10_000);
+const DEFAULT_PRICE_STEPS: (u64, u64) = (721, 10_000_000);
+const DEFAULT_MAX_TX_EX_UNITS: (u64, u64) = (14_000_000, 10_000_000_000);
+
+/// Protocol parameters which are relevant for building transactions
+#[derive(Debug, Clone)]
+pub struct ProtocolParams {
+    pub min_fee_a: u64,
+    pub min_fee_b: u64,
+    pub max_tx_size: u64,
+    pub key_deposit: u64,
+    pub coins_per_utxo_byte: u64,
+    /// maximum size in bytes of the serialized value of an output
+    pub max_value_size: u64,
+    /// collateral required by transactions running scripts, as a percentage
+    /// of their fee
+    pub collateral_percentage: u64,
+    pub max_collateral_inputs: u64,
+    /// price of a unit of memory and of a step, as numerator and denominator
+    pub price_mem: (u64, u64),
+    pub price_steps: (u64, u64),
+    /// execution budget of a whole transaction, as memory and steps
+    pub max_tx_ex_units: (u64, u64),
+    /// Plutus cost models, only known once an update setting them was seen
+    pub plutus_v1_cost_model: Option<Vec<i64>>,
+    pub plutus_v2_cost_model: Option<Vec<i64>>,
+}
+
+impl Default for ProtocolParams {
+    fn default() -> Self {
+        Self {
+            min_fee_a: DEFAULT_MIN_FEE_A,
+            min_fee_b: DEFAULT_MIN_FEE_B,
+            max_tx_size: DEFAULT_MAX_TX_SIZE,
+            key_deposit: DEFAULT_KEY_DEPOSIT,
+            coins_per_utxo_byte: DEFAULT_COINS_PER_UTXO_BYTE,
+            max_value_size: DEFAULT_MAX_VALUE_SIZE,
+            collateral_percentage: DEFAULT_COLLATERAL_PERCENTAGE,
+            max_collateral_inputs: DEFAULT_MAX_COLLATERAL_INPUTS,
+            price_mem: DEFAULT_PRICE_MEM,
+            price_steps: DEFAULT_PRICE_STEPS,
+            max_tx_ex_units: DEFAULT_MAX_TX_EX_UNITS,
+            plutus_v1_cost_model: None,
+            plutus_v2_cost_model: None,
+        }
+    }
+}
+
+impl ProtocolParams {
+    /// Load the effective protocol parameters by folding every update stored
+    /// in the wallet db in slot order over the defaults, so a value keeps the
+    /// last update which specified it
+    pub async fn load(wallet_db: &WalletDB) -> miette::Result<Self> {
+        let mut pparams = Self::default();
+
+        let updates = wallet_db
+            .fetch_all_protocol_parameters()
+            .await
+            .into_diagnostic()?;
+
+        for cbor in updates {
+            let update: Update = minicbor::decode(&cbor)
+                .into_diagnostic()
+                .context("decoding protocol parameters update")?;
+
+            pparams.apply_update(&update);
+        }
+
+        Ok(pparams)
+    }
+
+    /// Fold all the proposals contained in an update into the current values.
+    /// Proposals are submitted by each genesis delegate, so they will usually
+    /// agree on the values.
+    pub fn apply_update(&mut self, update: &Update) {
+        for (_, proposal) in update.proposed_protocol_parameter_updates.iter() {
+            if let Some(x) = proposal.minfee_a {
+                self.min_fee_a = x.into();
+            }
+
+            if let Some(x) = proposal.minfee_b {
+                self.min_fee_b = x.into();
+            }
+
+            if let Some(x) = proposal.max_transaction_size {
+                self.max_tx_size = x.into();
+            }
+
+            if let Some(x) = proposal.key_deposit {
+                self.key_deposit = x;
+            }
+
+            if let Some(x) = proposal.ada_per_utxo_byte {
+                self.coins_per_utxo_byte = x;
+            }
+
+            if let Some(x) = proposal.max_value_size {
+                self.max_value_size = x.into();
+            }
+
+            if let Some(x) = proposal.collateral_percentage {
+                self.collateral_percentage = x.into();
+            }
+
+            if let Some(x) = proposal.max_collateral_inputs {
+                self.max_collateral_inputs = x.into();
+            }
+
+            if let Some((mem, steps)) = proposal.execution_costs.as_ref().and_then(prices) {
+                self.price_mem = (mem.numerator, mem.denominator);
+                self.price_steps = (steps.numerator, steps.denominator);
+            }
+
+            if let Some(x) = &proposal.max_tx_ex_units {
+                self.max_tx_ex_units = (x.mem.into(), x.steps);
+            }
+
+            if let Some(x) = &proposal.cost_models_for_script_languages {
+                if let Some(model) = &x.plutus_v1 {
+                    self.plutus_v1_cost_model = Some(model.clone());
+                }
+
+                if let Some(model) = &x.plutus_v2 {
+                    self.plutus_v2_cost_model = Some(model.clone());
+                }
+            }
+        }
+    }
+
+    /// Minimum lovelace the ledger requires an output to hold, which grows
+    /// with its serialized size (datums and reference scripts included)
+    pub fn min_lovelace(&self, output: &Output) -> miette::Result<u64> {
+        let mut output = output.clone();
+
+        // the lovelace amount is part of the size, raise it until the
+        // output holds enough for its own encoding
+        loop {
+            let size = minicbor::to_vec(build_output(&output)?)
+                .into_diagnostic()?
+                .len() as u64;
+
+            // 160 bytes accounts for the input and map overhead of the utxo
+            // entry
+            let min = (160 + size) * self.coins_per_utxo_byte;
+
+            if output.lovelace >= min {
+                return Ok(min);
+            }
+
+            output.lovelace = min;
+        }
+    }
+
+    /// Size in bytes of the serialized value of an output, bounded by the
+    /// `max_value_size` parameter
+    pub fn value_size(&self, output: &Output) -> miette::Result<u64> {
+        let size = match build_output(output)? {
+            TransactionOutput::Legacy(x) => minicbor::to_vec(x.amount),
+            TransactionOutput::PostAlonzo(x) => minicbor::to_vec(x.value),
+        }
+        .into_diagnostic()?
+        .len();
+
+        Ok(size as u64)
+    }
+
+    /// Collateral a transaction running scripts has to provide for the
+    /// given fee
+    pub fn min_collateral(&self, fee: u64) -> u64 {
+        (fee as u128 * self.collateral_percentage as u128).div_ceil(100) as u64
+    }
+
+    /// Fee paid for the execution of scripts with the given budget
+    pub fn script_fee(&self, mem: u64, steps: u64) -> u64 {
+        let (mem_num, mem_den) = self.price_mem;
+        let (steps_num, steps_den) = self.price_steps;
+
+        // both terms are added as a single fraction and rounded up once, as
+        // the ledger does
+        let num = mem as u128 * mem_num as u128 * steps_den as u128
+            + steps as u128 * steps_num as u128 * mem_den as u128;
+        let den = mem_den as u128 * steps_den as u128;
+
+        num.div_ceil(den) as u64
+    }
+
+    /// CBOR of the known cost models, as expected by the script evaluator
+    pub fn cost_models_cbor(&self) -> miette::Result<Vec<u8>> {
+        let cost_models = CostMdls {
+            plutus_v1: self.plutus_v1_cost_model.clone(),
+            plutus_v2: self.plutus_v2_cost_model.clone(),
+        };
+
+        minicbor::to_vec(cost_models).into_diagnostic()
+    }
+}
+
+/// Memory and step prices of an update. `ExUnitPrices` keeps them private,
+/// so they're read back from its CBOR form.
+fn prices(x: &ExUnitPrices) -> Option<(RationalNumber, RationalNumber)> {
+    let cbor = minicbor::to_vec(x).ok()?;
+    minicbor::decode(&cbor).ok()
+}
+
+/// Ledger form of a staging output. pallas only builds outputs as part of a
+/// transaction, so it's taken from one holding just that output.
+fn build_output(output: &Output) -> miette::Result<TransactionOutput> {
+    let built = StagingTransaction::new()
+        .output(output.clone())
+        .build_babbage_raw()
+        .map_err(|e| miette!("output build failed: {e:?}"))?;
+
+    let tx: babbage::Tx = minicbor::decode(&built.tx_bytes.0)
+        .into_diagnostic()
+        .context("decoding built output")?;
+
+    tx.transaction_body
+        .outputs
+        .into_iter()
+        .next()
+        .ok_or(miette!("built transaction has no output"))
+}
+
+#[cfg(test)]
+mod tests {
+    use pallas::ledger::addresses::{
+        Address, Network, ShelleyAddress, ShelleyDelegationPart, ShelleyPaymentPart,
+    };
+
+    use super::*;
+
+    fn base_address() -> Address {
+        ShelleyAddress::new(
+            Network::Testnet,
+            ShelleyPaymentPart::key_hash([1; 28].into()),
+            ShelleyDelegationPart::key_hash([2; 28].into()),
+        )
+        .into()
+    }
+
+    #[test]
+    fn ada_only_min_lovelace() {
+        let pparams = ProtocolParams::default();
+
+        // map encoded output of 67 bytes: header, address and 5 byte amount
+        let expected = (160 + 67) * 4310;
+
+        let output = Output::new(base_address(), 0);
+        assert_eq!(pparams.min_lovelace(&output).unwrap(), expected);
+
+        let output = Output::new(base_address(), expected);
+        assert_eq!(pparams.min_lovelace(&output).unwrap(), expected);
+
+        // amounts over u32 take 4 more bytes
+        let output = Output::new(base_address(), 5_000_000_000);
+        assert_eq!(
+            pparams.min_lovelace(&output).unwrap(),
+            (160 + 67 + 4) * 4310
+        );
+    }
+
+    #[test]
+    fn assets_and_datums_raise_min_lovelace() {
+        let pparams = ProtocolParams::default();
+
+        let plain = pparams
+            .min_lovelace(&Output::new(base_address(), 0))
+            .unwrap();
+
+        let with_asset = Output::new(base_address(), 0)
+            .add_asset([3; 28].into(), b"token".to_vec(), 1)
+            .unwrap();
+        let with_asset = pparams.min_lovelace(&with_asset).unwrap();
+
+        let with_datum = Output::new(base_address(), 0).set_inline_datum(vec![0x40; 1]);
+        let with_datum = pparams.min_lovelace(&with_datum).unwrap();
+
+        assert!(with_asset > plain);
+        assert!(with_datum > plain);
+        assert_eq!((with_asset - plain) % pparams.coins_per_utxo_byte, 0);
+    }
+
+    #[test]
+    fn collateral() {
+        let pparams = ProtocolParams::default();
+
+        assert_eq!(pparams.min_collateral(170_000), 255_000);
+        assert_eq!(pparams.min_collateral(1), 2);
+        assert_eq!(pparams.min_collateral(0), 0);
+    }
+
+    #[test]
+    fn script_fee_rounds_up_once() {
+        let pparams = ProtocolParams::default();
+
+        assert_eq!(pparams.script_fee(1_000_000, 500_000_000), 57_700 + 36_050);
+        assert_eq!(pparams.script_fee(10_000, 0), 577);
+        assert_eq!(pparams.script_fee(1, 1), 1);
+        assert_eq!(pparams.script_fee(0, 0), 0);
+    }
+
+    #[test]
+    fn execution_prices() {
+        let rational = |numerator, denominator| RationalNumber {
+            numerator,
+            denominator,
+        };
+
+        let cbor = minicbor::to_vec((rational(577, 10_000), rational(721, 10_000_000))).unwrap();
+        let decoded: ExUnitPrices = minicbor::decode(&cbor).unwrap();
+
+        let (mem, steps) = prices(&decoded).unwrap();
+        assert_eq!((mem.numerator, mem.denominator), (577, 10_000));
+        assert_eq!((steps.numerator, steps.denominator), (721, 10_000_000));
+    }
+}
@@ -1,13 +1,22 @@
 use clap::Parser;
-use miette::{bail, IntoDiagnostic};
-use pallas::{txbuilder::BuiltTransaction, wallet::wrapper};
-use tracing::{info, instrument};
+use miette::{bail, Context, IntoDiagnostic};
+use pallas::{
+    codec::minicbor,
+    crypto::hash::Hasher,
+    ledger::primitives::babbage,
+    txbuilder::BuiltTransaction,
+    wallet::{wrapper, PrivateKey},
+};
+use tracing::{info, instrument, warn};
 
 use crate::wallet::{
     config::Wallet,
     dal::{entities::transaction::Status, WalletDB},
+    hd,
 };
 
+use super::witness::{add_key_signature, required_signers, required_stake_signers};
+
 pub fn gather_inputs(args: &mut Args) -> miette::Result<()> {
     let password = inquire::Password::new("password:")
         .with_help_message("the spending password of your wallet")
@@ -37,6 +46,18 @@ pub struct Args {
 
 #[instrument("sign", skip_all, fields())]
 pub async fn run(mut args: Args, ctx: &crate::Context) -> miette::Result<()> {
+    let wallet = Wallet::load_config(&ctx.dirs.root_dir, &args.wallet)?
+        .ok_or(miette::miette!("wallet doesn't exist"))?;
+
+    if wallet.keys.is_watch_only() {
+        bail!(
+            "wallet '{}' is watch-only and holds no keys, sign the transaction elsewhere and attach the signatures with `transaction import-witness {} {} <FILES>`",
+            wallet.name,
+            wallet.name,
+            args.id
+        );
+    }
+
     if args.interactive {
         gather_inputs(&mut args)?;
     }
@@ -46,9 +67,6 @@ pub async fn run(mut args: Args, ctx: &crate::Context) -> miette::Result<()> {
         None => bail!("password is required"),
     };
 
-    let wallet = Wallet::load_config(&ctx.dirs.root_dir, &args.wallet)?
-        .ok_or(miette::miette!("wallet doesn't exist"))?;
-
     let wallet_db = WalletDB::open(&wallet.name, &Wallet::dir(&ctx.dirs.root_dir, &wallet.name))
         .await
         .into_diagnostic()?;
@@ -67,14 +85,13 @@ pub async fn run(mut args: Args, ctx: &crate::Context) -> miette::Result<()> {
     let mut built_tx: BuiltTransaction =
         serde_json::from_slice(&record.tx_json).into_diagnostic()?;
 
-    let privkey = wrapper::decrypt_private_key(
-        password,
-        hex::decode(wallet.keys.private_encrypted)
-            .map_err(|_| miette::miette!("malformed encrypted private key"))?,
-    )
-    .map_err(|_| miette::miette!("could not decrypt private key"))?;
+    let tx: babbage::Tx = minicbor::decode(&built_tx.tx_bytes.0)
+        .into_diagnostic()
+        .context("decoding built transaction")?;
 
-    built_tx = built_tx.sign(privkey).into_diagnostic()?;
+    for key in signing_keys(&wallet, &wallet_db, &tx, password).await? {
+        built_tx = add_key_signature(built_tx, &key)?;
+    }
 
     // update db
 
@@ -91,3 +108,87 @@ pub async fn run(mut args: Args, ctx: &crate::Context) -> miette::Result<()> {
 
     Ok(())
 }
+
+/// Keys of the wallet among the signers the transaction requires: the main
+/// payment key, the keys of other derived addresses and the stake key
+pub async fn signing_keys(
+    wallet: &Wallet,
+    wallet_db: &WalletDB,
+    tx: &babbage::Tx,
+    password: &String,
+) -> miette::Result<Vec<PrivateKey>> {
+    let private_encrypted = wallet
+        .keys
+        .private_encrypted
+        .as_ref()
+        .ok_or(miette::miette!("wallet is watch-only and holds no keys"))?;
+
+    let required = required_signers(tx, wallet_db).await?;
+
+    let mut keys = vec![];
+
+    let payment = decrypt_key(password, private_encrypted)?;
+
+    if required.contains_key(&Hasher::<224>::hash(payment.public_key().as_ref())) {
+        keys.push(payment);
+    }
+
+    // required signers which are other derived addresses of an HD wallet need
+    // the keys of those addresses
+
+    let mut derived = vec![];
+
+    for signer in required.keys() {
+        let address = wallet_db
+            .fetch_wallet_address_by_cred(signer.as_ref())
+            .await
+            .into_diagnostic()?;
+
+        if let Some(x) = address {
+            let path = (x.role as u32, x.address_index as u32);
+
+            if path != (hd::ROLE_EXTERNAL, 0) {
+                derived.push(path);
+            }
+        }
+    }
+
+    if let (Some(root_encrypted), false) = (&wallet.keys.root_encrypted, derived.is_empty()) {
+        let root = hd::decrypt_root_key(
+            password,
+            hex::decode(root_encrypted)
+                .map_err(|_| miette::miette!("malformed encrypted root key"))?,
+        )?;
+
+        let account = hd::derive_account(&root, 0);
+
+        for (role, index) in derived {
+            keys.push(hd::derive_key(&account, role, index).to_ed25519_private_key());
+        }
+    }
+
+    // certificates and withdrawals also need a witness from the stake key
+
+    for signer in required_stake_signers(&tx.transaction_body) {
+        let is_wallet_key = wallet
+            .keys
+            .stake_key_hash
+            .as_ref()
+            .is_some_and(|x| *x == signer.to_string());
+
+        match (&wallet.keys.stake_private_encrypted, is_wallet_key) {
+            (Some(encrypted), true) => keys.push(decrypt_key(password, encrypted)?),
+            _ => warn!(%signer, "transaction requires a stake key witness not held by the wallet"),
+        }
+    }
+
+    Ok(keys)
+}
+
+fn decrypt_key(password: &String, encrypted: &str) -> miette::Result<PrivateKey> {
+    wrapper::decrypt_private_key(
+        password,
+        hex::decode(encrypted).map_err(|_| miette::miette!("malformed encrypted private key"))?,
+    )
+    .map_err(|_| miette::miette!("could not decrypt private key"))
+}
@@ -0,0 +1,552 @@
+use miette::{bail, miette, Context, IntoDiagnostic};
+use pallas::{
+    codec::{minicbor, utils::KeyValuePairs},
+    crypto::hash::{Hash, Hasher},
+    ledger::{addresses::Address, primitives::babbage},
+    txbuilder::{BuildBabbage, BuiltTransaction, Input, StagingTransaction},
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// Staging transaction as stored in the wallet db. Wraps the pallas staging
+/// transaction together with the parts of the body it can't stage yet
+/// (certificates, withdrawals and total collateral), which are added to the
+/// body once built.
+#[derive(Serialize, Deserialize)]
+pub struct StagingTx {
+    #[serde(flatten)]
+    pub tx: StagingTransaction,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub certificates: Vec<Certificate>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub withdrawals: Vec<Withdrawal>,
+    /// lovelace lost by the collateral if the scripts fail, declared along
+    /// with the collateral return
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_collateral: Option<u64>,
+    /// index of the change output added by auto balancing, which balancing
+    /// again replaces
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub change_output: Option<usize>,
+    /// fee set by auto balancing, which balancing again recomputes. Any other
+    /// fee was set by the user.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub balanced_fee: Option<u64>,
+    /// cost models of the Plutus languages used by the transaction, part of
+    /// the script data hash. Filled in when building, never stored.
+    #[serde(skip)]
+    pub cost_models: Vec<(PlutusVersion, Vec<i64>)>,
+}
+
+impl Clone for StagingTx {
+    fn clone(&self) -> Self {
+        Self {
+            tx: copy_staging(&self.tx),
+            certificates: self.certificates.clone(),
+            withdrawals: self.withdrawals.clone(),
+            total_collateral: self.total_collateral,
+            change_output: self.change_output,
+            balanced_fee: self.balanced_fee,
+            cost_models: self.cost_models.clone(),
+        }
+    }
+}
+
+/// Copy of a pallas staging transaction, which isn't Clone, made through the
+/// json form it's stored in
+pub fn copy_staging(tx: &StagingTransaction) -> StagingTransaction {
+    serde_json::to_value(tx)
+        .and_then(serde_json::from_value)
+        .expect("staging transaction json roundtrip")
+}
+
+/// Execution units of a staging redeemer. pallas doesn't export their type,
+/// so they're made from its serde form.
+pub fn ex_units<T: DeserializeOwned>(mem: u32, steps: u64) -> T {
+    serde_json::from_value(serde_json::json!({ "mem": mem, "steps": steps }))
+        .expect("execution units from json")
+}
+
+/// Copy of a staging transaction input, which pallas doesn't make Clone
+pub fn copy_input(input: &Input) -> Input {
+    Input::new(input.tx_hash.0.into(), input.txo_index)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlutusVersion {
+    V1,
+    V2,
+}
+
+// named after the ledger certificates
+#[allow(clippy::enum_variant_names)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Certificate {
+    StakeRegistration {
+        stake_key_hash: Hash<28>,
+    },
+    StakeDeregistration {
+        stake_key_hash: Hash<28>,
+    },
+    StakeDelegation {
+        stake_key_hash: Hash<28>,
+        pool_id: Hash<28>,
+    },
+}
+
+impl Certificate {
+    fn to_primitive(&self) -> babbage::Certificate {
+        match self {
+            Certificate::StakeRegistration { stake_key_hash } => {
+                babbage::Certificate::StakeRegistration(babbage::StakeCredential::AddrKeyhash(
+                    *stake_key_hash,
+                ))
+            }
+            Certificate::StakeDeregistration { stake_key_hash } => {
+                babbage::Certificate::StakeDeregistration(babbage::StakeCredential::AddrKeyhash(
+                    *stake_key_hash,
+                ))
+            }
+            Certificate::StakeDelegation {
+                stake_key_hash,
+                pool_id,
+            } => babbage::Certificate::StakeDelegation(
+                babbage::StakeCredential::AddrKeyhash(*stake_key_hash),
+                *pool_id,
+            ),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Withdrawal {
+    /// raw reward account bytes (header and stake credential) as hex
+    pub reward_account: String,
+    pub amount: u64,
+}
+
+impl Withdrawal {
+    fn reward_account_bytes(&self) -> miette::Result<Vec<u8>> {
+        hex::decode(&self.reward_account)
+            .into_diagnostic()
+            .context("parsing reward account hex")
+    }
+}
+
+/// Lovelace certificates and withdrawals add to (positive) or take from
+/// (negative) the balance of a transaction: withdrawn rewards and deposit
+/// refunds minus new deposits
+pub fn implicit_lovelace(
+    certificates: &[babbage::Certificate],
+    withdrawals: impl IntoIterator<Item = u64>,
+    key_deposit: u64,
+) -> i128 {
+    let mut total: i128 = withdrawals.into_iter().map(i128::from).sum();
+
+    for cert in certificates {
+        match cert {
+            babbage::Certificate::StakeRegistration(_) => total -= key_deposit as i128,
+            babbage::Certificate::StakeDeregistration(_) => total += key_deposit as i128,
+            _ => (),
+        }
+    }
+
+    total
+}
+
+/// Parse a reward account given either as bech32 stake address or as the
+/// hex of its raw bytes
+pub fn parse_reward_account(value: &str) -> miette::Result<Vec<u8>> {
+    let address = match Address::from_bech32(value) {
+        Ok(x) => x,
+        Err(_) => {
+            let bytes = hex::decode(value)
+                .map_err(|_| miette!("reward account must be a stake address or hex bytes"))?;
+
+            Address::from_bytes(&bytes)
+                .into_diagnostic()
+                .context("parsing reward account bytes")?
+        }
+    };
+
+    match address {
+        Address::Stake(_) => Ok(address.to_vec()),
+        _ => bail!("withdrawal account is not a reward address"),
+    }
+}
+
+impl StagingTx {
+    /// Lovelace the certificates and withdrawals of the transaction add to
+    /// or take from its balance, see [`implicit_lovelace`]
+    pub fn implicit_lovelace(&self, key_deposit: u64) -> i128 {
+        let certificates: Vec<_> = self.certificates.iter().map(|x| x.to_primitive()).collect();
+
+        implicit_lovelace(
+            &certificates,
+            self.withdrawals.iter().map(|x| x.amount),
+            key_deposit,
+        )
+    }
+
+    /// Whether every redeemer has its execution units, which pallas needs to
+    /// build the transaction
+    fn has_budgets(&self) -> bool {
+        self.tx
+            .redeemers
+            .iter()
+            .flat_map(|x| x.values())
+            .all(|(_, ex_units)| ex_units.is_some())
+    }
+
+    /// Build the transaction and add the certificates, withdrawals, total
+    /// collateral and script data hash to the resulting body, recomputing the
+    /// transaction hash
+    pub fn build(&self) -> miette::Result<BuiltTransaction> {
+        if !self.has_budgets() {
+            bail!("redeemers without execution units, evaluate the scripts or set them explicitly");
+        }
+
+        let mut built = copy_staging(&self.tx)
+            .build_babbage_raw()
+            .map_err(|e| miette!("tx build failed: {e:?}"))?;
+
+        let has_script_data = self.tx.redeemers.is_some() || self.tx.datums.is_some();
+
+        if self.certificates.is_empty()
+            && self.withdrawals.is_empty()
+            && self.total_collateral.is_none()
+            && !has_script_data
+        {
+            return Ok(built);
+        }
+
+        let mut tx: babbage::Tx = minicbor::decode(&built.tx_bytes.0)
+            .into_diagnostic()
+            .context("decoding built transaction")?;
+
+        if !self.certificates.is_empty() {
+            tx.transaction_body.certificates =
+                Some(self.certificates.iter().map(|x| x.to_primitive()).collect());
+        }
+
+        if !self.withdrawals.is_empty() {
+            let withdrawals = self
+                .withdrawals
+                .iter()
+                .map(|x| Ok((x.reward_account_bytes()?.into(), x.amount)))
+                .collect::<miette::Result<Vec<_>>>()?;
+
+            tx.transaction_body.withdrawals = Some(KeyValuePairs::from(withdrawals));
+        }
+
+        if self.total_collateral.is_some() {
+            tx.transaction_body.total_collateral = self.total_collateral;
+        }
+
+        if has_script_data {
+            tx.transaction_body.script_data_hash =
+                Some(self.script_data_hash(&tx.transaction_witness_set)?);
+        }
+
+        built.tx_hash.0 = *Hasher::<256>::hash_cbor(&tx.transaction_body);
+        built.tx_bytes.0 = minicbor::to_vec(&tx).into_diagnostic()?;
+
+        Ok(built)
+    }
+
+    /// Hash of the redeemers, datums and language views of the cost models
+    /// used by the scripts, which commits the body to the script data
+    fn script_data_hash(&self, witness_set: &babbage::WitnessSet) -> miette::Result<Hash<32>> {
+        // without redeemers an empty list takes their place
+        let mut preimage = match &witness_set.redeemer {
+            Some(x) => minicbor::to_vec(x),
+            None => minicbor::to_vec(Vec::<babbage::Redeemer>::new()),
+        }
+        .into_diagnostic()?;
+
+        if let Some(datums) = &witness_set.plutus_data {
+            preimage.extend(minicbor::to_vec(datums).into_diagnostic()?);
+        }
+
+        preimage.extend(language_views(&self.cost_models).into_diagnostic()?);
+
+        Ok(Hasher::<256>::hash(&preimage))
+    }
+}
+
+/// Language views encoding of the cost models as defined by the Alonzo and
+/// Babbage ledgers, including their quirks: PlutusV1 uses a serialized
+/// language id as key and an indefinite list wrapped in a bytestring as value
+fn language_views(
+    cost_models: &[(PlutusVersion, Vec<i64>)],
+) -> Result<Vec<u8>, minicbor::encode::Error<std::convert::Infallible>> {
+    let mut sorted = cost_models.to_vec();
+
+    // canonical key order, the PlutusV2 key (uint 1) is shorter than the
+    // PlutusV1 one (bytes 0x00)
+    sorted.sort_by_key(|(version, _)| match version {
+        PlutusVersion::V2 => 0,
+        PlutusVersion::V1 => 1,
+    });
+
+    let mut e = minicbor::Encoder::new(Vec::new());
+    e.map(sorted.len() as u64)?;
+
+    for (version, model) in sorted {
+        match version {
+            PlutusVersion::V1 => {
+                let mut inner = minicbor::Encoder::new(Vec::new());
+                inner.begin_array()?;
+                for x in model {
+                    inner.i64(x)?;
+                }
+                inner.end()?;
+
+                e.bytes(&[0x00])?;
+                e.bytes(&inner.into_writer())?;
+            }
+            PlutusVersion::V2 => {
+                e.u8(1)?;
+                e.array(model.len() as u64)?;
+                for x in model {
+                    e.i64(x)?;
+                }
+            }
+        }
+    }
+
+    Ok(e.into_writer())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // preview transactions, also used by the uplc evaluation tests, with the
+    // cost models they were built against
+
+    const V1_TX: &str = "84a80081825820275b5da338c8b899035081eb34bfa950b634911a5dd3271b3ad6cf4c2bba0c50010182825839000af00cc47500bb64cfffb783e8c42f746b4e8b8a70ede9c08c7113acf3bde34d1041f5a2076ef9aa6cf4539ab1a96ed462a0300acbdb65d5821a00111958a1581c1e8bca1fa1d937f408afe2fd4dbf343ab7a09cf07984071ed95b3c92a1400a825839000af00cc47500bb64cfffb783e8c42f746b4e8b8a70ede9c08c7113acf3bde34d1041f5a2076ef9aa6cf4539ab1a96ed462a0300acbdb65d51a029f7b29021a0002bc5009a1581c1e8bca1fa1d937f408afe2fd4dbf343ab7a09cf07984071ed95b3c92a1400a0b58205013dbe72526511f63b0c4a235fbbc5d09d11d42f310113aaab1a28e01e0bde60d81825820275b5da338c8b899035081eb34bfa950b634911a5dd3271b3ad6cf4c2bba0c500110825839000af00cc47500bb64cfffb783e8c42f746b4e8b8a70ede9c08c7113acf3bde34d1041f5a2076ef9aa6cf4539ab1a96ed462a0300acbdb65d51a02af3659111a00041a78a30081825820065dd553fbe4e240a8f819bb9e333a7483de4a22b65c7fb6a95ce9450f84dff758401679b607eabef3dbbc9ac0abb03afb3a979ea32243bb5b99e299290d709bb4a6c2aa528c447c2db610a103cc9c0e7c018caa4fc8322d8ec217620e6d4bc2ef0b03815453010000322233335734600693124c4c931250010581840100d87980821909611a00094d78f5f6";
+
+    const V1_COST_MODEL: [i64; 166] = [
+        205665, 812, 1, 1, 1000, 571, 0, 1, 1000, 24177, 4, 1, 1000, 32, 117366, 10475, 4, 23000,
+        100, 23000, 100, 23000, 100, 23000, 100, 23000, 100, 23000, 100, 100, 100, 23000, 100,
+        19537, 32, 175354, 32, 46417, 4, 221973, 511, 0, 1, 89141, 32, 497525, 14068, 4, 2, 196500,
+        453240, 220, 0, 1, 1, 1000, 28662, 4, 2, 245000, 216773, 62, 1, 1060367, 12586, 1, 208512,
+        421, 1, 187000, 1000, 52998, 1, 80436, 32, 43249, 32, 1000, 32, 80556, 1, 57667, 4, 1000,
+        10, 197145, 156, 1, 197145, 156, 1, 204924, 473, 1, 208896, 511, 1, 52467, 32, 64832, 32,
+        65493, 32, 22558, 32, 16563, 32, 76511, 32, 196500, 453240, 220, 0, 1, 1, 69522, 11687, 0,
+        1, 60091, 32, 196500, 453240, 220, 0, 1, 1, 196500, 453240, 220, 0, 1, 1, 806990, 30482, 4,
+        1927926, 82523, 4, 265318, 0, 4, 0, 85931, 32, 205665, 812, 1, 1, 41182, 32, 212342, 32,
+        31220, 32, 32696, 32, 43357, 32, 32247, 32, 38314, 32, 9462713, 1021, 10,
+    ];
+
+    const V2_TX: &str = "84a80081825820275b5da338c8b899035081eb34bfa950b634911a5dd3271b3ad6cf4c2bba0c50010182825839000af00cc47500bb64cfffb783e8c42f746b4e8b8a70ede9c08c7113acf3bde34d1041f5a2076ef9aa6cf4539ab1a96ed462a0300acbdb65d5821a00111958a1581c74184f56338d719f7888d9719ff0c8a9d9550c0f46165e09831cdba9a1400a825839000af00cc47500bb64cfffb783e8c42f746b4e8b8a70ede9c08c7113acf3bde34d1041f5a2076ef9aa6cf4539ab1a96ed462a0300acbdb65d51a029d7d51021a0004ba2809a1581c74184f56338d719f7888d9719ff0c8a9d9550c0f46165e09831cdba9a1400a0b5820901a846f82e9b756b94686bc56109ca62e7724cbb99091b0b971bcb1c8ce98530d81825820275b5da338c8b899035081eb34bfa950b634911a5dd3271b3ad6cf4c2bba0c500110825839000af00cc47500bb64cfffb783e8c42f746b4e8b8a70ede9c08c7113acf3bde34d1041f5a2076ef9aa6cf4539ab1a96ed462a0300acbdb65d51a02ac3995111a0007173ca30081825820065dd553fbe4e240a8f819bb9e333a7483de4a22b65c7fb6a95ce9450f84dff7584063f5dd2edfd43a5c0fc066d0230e5a5ba4b201bc75b22f94bfa5344c0ff9cd08c0018b5cc0f0e34cd2ab4e8c59954834330ba63a1b875c0cb3b5fd1ac68db4060581840100d87980821a0008e6681a0ae626b7068159078259077f0100003232323232323232323232323232332232323232322232325335332201a3333573466e1cd55ce9baa00448000805c8c98c805ccd5ce00c00b80a9999ab9a3370e6aae7540092000233221233001003002323232323232323232323232323333573466e1cd55cea8062400046666666666664444444444442466666666666600201a01801601401201000e00c00a00800600466a02802a6ae854030cd4050054d5d0a80599a80a00b1aba1500a3335501875ca02e6ae854024ccd54061d7280b9aba1500833501401f35742a00e666aa030040eb4d5d0a8031919191999ab9a3370e6aae75400920002332212330010030023232323333573466e1cd55cea8012400046644246600200600466a054eb4d5d0a80118159aba135744a004464c6405a66ae700b80b40ac4d55cf280089baa00135742a0046464646666ae68cdc39aab9d5002480008cc8848cc00400c008cd40a9d69aba15002302b357426ae8940088c98c80b4cd5ce01701681589aab9e5001137540026ae84d5d1280111931901499ab9c02a029027135573ca00226ea8004d5d0a80299a80a3ae35742a008666aa03003840026ae85400cccd54061d710009aba15002301e357426ae8940088c98c8094cd5ce01301281189aba25001135744a00226ae8940044d5d1280089aba25001135744a00226ae8940044d5d1280089aba25001135744a00226aae7940044dd50009aba15002300e357426ae8940088c98c805ccd5ce00c00b80a880b09931900b19ab9c4910350543500016135573ca00226ea800448c88c008dd6000990009aa80a911999aab9f0012500a233500930043574200460066ae880080508c8c8cccd5cd19b8735573aa004900011991091980080180118061aba150023005357426ae8940088c98c8050cd5ce00a80a00909aab9e5001137540024646464646666ae68cdc39aab9d5004480008cccc888848cccc00401401000c008c8c8c8cccd5cd19b8735573aa0049000119910919800801801180a9aba1500233500f014357426ae8940088c98c8064cd5ce00d00c80b89aab9e5001137540026ae854010ccd54021d728039aba150033232323333573466e1d4005200423212223002004357426aae79400c8cccd5cd19b875002480088c84888c004010dd71aba135573ca00846666ae68cdc3a801a400042444006464c6403666ae7007006c06406005c4d55cea80089baa00135742a00466a016eb8d5d09aba2500223263201533573802c02a02626ae8940044d5d1280089aab9e500113754002266aa002eb9d6889119118011bab00132001355012223233335573e0044a010466a00e66442466002006004600c6aae754008c014d55cf280118021aba200301213574200222440042442446600200800624464646666ae68cdc3a800a40004642446004006600a6ae84d55cf280191999ab9a3370ea0049001109100091931900819ab9c01101000e00d135573aa00226ea80048c8c8cccd5cd19b875001480188c848888c010014c01cd5d09aab9e500323333573466e1d400920042321222230020053009357426aae7940108cccd5cd19b875003480088c848888c004014c01cd5d09aab9e500523333573466e1d40112000232122223003005375c6ae84d55cf280311931900819ab9c01101000e00d00c00b135573aa00226ea80048c8c8cccd5cd19b8735573aa004900011991091980080180118029aba15002375a6ae84d5d1280111931900619ab9c00d00c00a135573ca00226ea80048c8cccd5cd19b8735573aa002900011bae357426aae7940088c98c8028cd5ce00580500409baa001232323232323333573466e1d4005200c21222222200323333573466e1d4009200a21222222200423333573466e1d400d2008233221222222233001009008375c6ae854014dd69aba135744a00a46666ae68cdc3a8022400c4664424444444660040120106eb8d5d0a8039bae357426ae89401c8cccd5cd19b875005480108cc8848888888cc018024020c030d5d0a8049bae357426ae8940248cccd5cd19b875006480088c848888888c01c020c034d5d09aab9e500b23333573466e1d401d2000232122222223005008300e357426aae7940308c98c804ccd5ce00a00980880800780700680600589aab9d5004135573ca00626aae7940084d55cf280089baa0012323232323333573466e1d400520022333222122333001005004003375a6ae854010dd69aba15003375a6ae84d5d1280191999ab9a3370ea0049000119091180100198041aba135573ca00c464c6401866ae700340300280244d55cea80189aba25001135573ca00226ea80048c8c8cccd5cd19b875001480088c8488c00400cdd71aba135573ca00646666ae68cdc3a8012400046424460040066eb8d5d09aab9e500423263200933573801401200e00c26aae7540044dd500089119191999ab9a3370ea00290021091100091999ab9a3370ea00490011190911180180218031aba135573ca00846666ae68cdc3a801a400042444004464c6401466ae7002c02802001c0184d55cea80089baa0012323333573466e1d40052002200723333573466e1d40092000212200123263200633573800e00c00800626aae74dd5000a4c2400292010350543100122002112323001001223300330020020011f5f6";
+
+    const V2_COST_MODEL: [i64; 175] = [
+        205665,
+        812,
+        1,
+        1,
+        1000,
+        571,
+        0,
+        1,
+        1000,
+        24177,
+        4,
+        1,
+        1000,
+        32,
+        117366,
+        10475,
+        4,
+        23000,
+        100,
+        23000,
+        100,
+        23000,
+        100,
+        23000,
+        100,
+        23000,
+        100,
+        23000,
+        100,
+        100,
+        100,
+        23000,
+        100,
+        19537,
+        32,
+        175354,
+        32,
+        46417,
+        4,
+        221973,
+        511,
+        0,
+        1,
+        89141,
+        32,
+        497525,
+        14068,
+        4,
+        2,
+        196500,
+        453240,
+        220,
+        0,
+        1,
+        1,
+        1000,
+        28662,
+        4,
+        2,
+        245000,
+        216773,
+        62,
+        1,
+        1060367,
+        12586,
+        1,
+        208512,
+        421,
+        1,
+        187000,
+        1000,
+        52998,
+        1,
+        80436,
+        32,
+        43249,
+        32,
+        1000,
+        32,
+        80556,
+        1,
+        57667,
+        4,
+        1000,
+        10,
+        197145,
+        156,
+        1,
+        197145,
+        156,
+        1,
+        204924,
+        473,
+        1,
+        208896,
+        511,
+        1,
+        52467,
+        32,
+        64832,
+        32,
+        65493,
+        32,
+        22558,
+        32,
+        16563,
+        32,
+        76511,
+        32,
+        196500,
+        453240,
+        220,
+        0,
+        1,
+        1,
+        69522,
+        11687,
+        0,
+        1,
+        60091,
+        32,
+        196500,
+        453240,
+        220,
+        0,
+        1,
+        1,
+        196500,
+        453240,
+        220,
+        0,
+        1,
+        1,
+        1159724,
+        392670,
+        0,
+        2,
+        806990,
+        30482,
+        4,
+        1927926,
+        82523,
+        4,
+        265318,
+        0,
+        4,
+        0,
+        85931,
+        32,
+        205665,
+        812,
+        1,
+        1,
+        41182,
+        32,
+        212342,
+        32,
+        31220,
+        32,
+        32696,
+        32,
+        43357,
+        32,
+        32247,
+        32,
+        38314,
+        32,
+        20000000000,
+        20000000000,
+        9462713,
+        1021,
+        10,
+        20000000000,
+        0,
+        20000000000,
+    ];
+
+    fn assert_script_data_hash(tx: &str, version: PlutusVersion, cost_model: &[i64]) {
+        let tx: babbage::Tx = minicbor::decode(&hex::decode(tx).unwrap()).unwrap();
+
+        let staging = StagingTx {
+            tx: StagingTransaction::new(),
+            certificates: vec![],
+            withdrawals: vec![],
+            total_collateral: None,
+            change_output: None,
+            balanced_fee: None,
+            cost_models: vec![(version, cost_model.to_vec())],
+        };
+
+        let hash = staging
+            .script_data_hash(&tx.transaction_witness_set)
+            .unwrap();
+
+        assert_eq!(Some(hash), tx.transaction_body.script_data_hash);
+    }
+
+    #[test]
+    fn plutus_v1_script_data_hash() {
+        assert_script_data_hash(V1_TX, PlutusVersion::V1, &V1_COST_MODEL);
+    }
+
+    #[test]
+    fn plutus_v2_script_data_hash() {
+        assert_script_data_hash(V2_TX, PlutusVersion::V2, &V2_COST_MODEL);
+    }
+}
@@ -2,14 +2,20 @@ use std::{str::FromStr, time::Duration};
 
 use clap::Parser;
 use miette::{bail, Context, IntoDiagnostic};
+use pallas::txbuilder::BuiltTransaction;
 use reqwest::header;
 use tracing::{info, instrument};
 
-use crate::wallet::{
-    config::{SubmitApi, Wallet},
-    dal::WalletDB,
+use crate::{
+    chain::config::Chain,
+    wallet::{
+        config::{SubmitApi, Wallet},
+        dal::WalletDB,
+    },
 };
 
+use super::{pparams::ProtocolParams, validate};
+
 #[derive(Parser)]
 pub struct Args {
     /// name of the wallet
@@ -17,6 +23,11 @@ pub struct Args {
     wallet: String,
     /// transaction id
     id: i32,
+
+    /// skip checking the transaction against the ledger rules before
+    /// submitting it
+    #[arg(long, action)]
+    no_validate: bool,
 }
 
 #[instrument("submit", skip_all, fields(args))]
@@ -38,7 +49,23 @@ pub async fn run(args: Args, ctx: &crate::Context) -> miette::Result<()> {
         bail!("transaction is not ready to submit yet")
     }
 
-    let api = wallet.submit_api.unwrap_or_default();
+    if !args.no_validate {
+        let built_tx: BuiltTransaction =
+            serde_json::from_slice(&transaction.tx_json).into_diagnostic()?;
+
+        let pparams = ProtocolParams::load(&wallet_db).await?;
+
+        validate::validate(&built_tx, &wallet_db, &pparams, true).await?;
+    }
+
+    // the chain may provide a submit api for wallets which don't set one
+    let api = match wallet.submit_api {
+        Some(api) => api,
+        None => match chain_submit_api(&wallet, ctx)? {
+            Some(api) => api,
+            None => bail!("no submit api configured, set `submit_api` in the wallet config or recreate its chain with `--submit-api <URL>`"),
+        },
+    };
 
     let headers = get_headers(&api)?;
 
@@ -72,6 +99,27 @@ pub async fn run(args: Args, ctx: &crate::Context) -> miette::Result<()> {
     Ok(())
 }
 
+fn chain_submit_api(wallet: &Wallet, ctx: &crate::Context) -> miette::Result<Option<SubmitApi>> {
+    let Some(chain_name) = &wallet.chain else {
+        return Ok(None);
+    };
+
+    let Some(chain) = Chain::load_config(&ctx.dirs.root_dir, chain_name)? else {
+        return Ok(None);
+    };
+
+    // chains of a well-known network fall back to the preset's submit api
+    let url = chain
+        .submit_api
+        .clone()
+        .or_else(|| chain.preset()?.submit_api.map(String::from));
+
+    Ok(url.map(|url| SubmitApi {
+        url,
+        headers: Default::default(),
+    }))
+}
+
 fn get_headers(api: &SubmitApi) -> miette::Result<header::HeaderMap> {
     let mut headers = header::HeaderMap::new();
 
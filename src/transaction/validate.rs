@@ -0,0 +1,670 @@
+use std::fmt;
+
+use clap::Parser;
+use miette::{bail, miette, Context, Diagnostic, IntoDiagnostic};
+use pallas::{
+    codec::minicbor,
+    crypto::hash::{Hash, Hasher},
+    ledger::{
+        primitives::babbage,
+        traverse::{Era, MultiEraOutput},
+    },
+    txbuilder::BuiltTransaction,
+};
+use tracing::{info, instrument, warn};
+
+use crate::wallet::{
+    config::Wallet,
+    dal::{
+        entities::{transaction::Status, utxo::Model as UtxoModel},
+        WalletDB,
+    },
+    multisig,
+};
+
+use super::{
+    fee,
+    pparams::ProtocolParams,
+    staging,
+    value::Value,
+    witness::{self, SignerSource},
+};
+
+#[derive(Parser)]
+pub struct Args {
+    /// name of the wallet
+    #[arg(env = "CARDAMINAL_DEFAULT_WALLET")]
+    wallet: String,
+
+    /// transaction id
+    id: i32,
+}
+
+#[instrument("validate", skip_all, fields(wallet=args.wallet,id=args.id))]
+pub async fn run(args: Args, ctx: &crate::Context) -> miette::Result<()> {
+    let wallet = Wallet::load_config(&ctx.dirs.root_dir, &args.wallet)?
+        .ok_or(miette::miette!("wallet doesn't exist"))?;
+
+    let wallet_db = WalletDB::open(&wallet.name, &Wallet::dir(&ctx.dirs.root_dir, &wallet.name))
+        .await
+        .into_diagnostic()?;
+
+    let record = wallet_db
+        .fetch_by_id(&args.id)
+        .await
+        .into_diagnostic()?
+        .ok_or(miette::miette!("transaction doesn't exist"))?;
+
+    if record.status == Status::Staging {
+        bail!("transaction must be built before validating")
+    }
+
+    let built_tx: BuiltTransaction = serde_json::from_slice(&record.tx_json).into_diagnostic()?;
+
+    let pparams = ProtocolParams::load(&wallet_db).await?;
+
+    validate(
+        &built_tx,
+        &wallet_db,
+        &pparams,
+        record.status != Status::Built,
+    )
+    .await?;
+
+    info!("transaction is valid");
+
+    Ok(())
+}
+
+/// Ledger rules a transaction breaks, each reported as its own diagnostic
+#[derive(Debug)]
+pub struct ValidationFailed(Vec<miette::ErrReport>);
+
+impl fmt::Display for ValidationFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "transaction breaks {} ledger rule(s)", self.0.len())
+    }
+}
+
+impl std::error::Error for ValidationFailed {}
+
+impl Diagnostic for ValidationFailed {
+    fn related<'a>(&'a self) -> Option<Box<dyn Iterator<Item = &'a dyn Diagnostic> + 'a>> {
+        Some(Box::new(
+            self.0.iter().map(|x| x.as_ref() as &dyn Diagnostic),
+        ))
+    }
+}
+
+/// Check a built transaction against the phase-1 ledger rules, as far as the
+/// wallet knows the outputs it spends and the tip of the chain. Witnesses
+/// are only checked once the transaction is expected to be signed, before
+/// that the fee and size account for the missing signatures.
+pub async fn validate(
+    built: &BuiltTransaction,
+    wallet_db: &WalletDB,
+    pparams: &ProtocolParams,
+    signed: bool,
+) -> miette::Result<()> {
+    let tx: babbage::Tx = minicbor::decode(&built.tx_bytes.0)
+        .into_diagnostic()
+        .context("decoding built transaction")?;
+
+    let body = &tx.transaction_body;
+    let witness_set = &tx.transaction_witness_set;
+
+    let mut failures = vec![];
+
+    // inputs the wallet knows about, others can't be checked here and are
+    // left to the node
+
+    // unknown once any input can't be resolved
+    let mut consumed = Some(Value::default());
+
+    for input in body.inputs.iter() {
+        match resolve(wallet_db, input).await? {
+            Some(utxo) => {
+                if let Some(x) = consumed.as_mut() {
+                    x.add(&utxo_value(&utxo)?);
+                }
+            }
+            None => {
+                warn!(
+                    tx = %input.transaction_id,
+                    index = input.index,
+                    "input unknown to the wallet or already spent, value preservation not checked"
+                );
+                consumed = None;
+            }
+        }
+    }
+
+    for input in body.reference_inputs.iter().flatten() {
+        if resolve(wallet_db, input).await?.is_none() {
+            warn!(
+                tx = %input.transaction_id,
+                index = input.index,
+                "reference input unknown to the wallet or already spent"
+            );
+        }
+    }
+
+    // value is preserved
+
+    if let Some(mut balance) = consumed {
+        balance.add(&mint_value(body));
+        balance.lovelace += staging::implicit_lovelace(
+            body.certificates.as_deref().unwrap_or_default(),
+            body.withdrawals
+                .iter()
+                .flat_map(|x| x.iter())
+                .map(|(_, amount)| *amount),
+            pparams.key_deposit,
+        );
+        balance.lovelace -= body.fee as i128;
+
+        for output in body.outputs.iter() {
+            balance.sub(&output_value(output)?);
+        }
+
+        balance.normalize();
+
+        if balance != Value::default() {
+            failures.push(miette!(
+                "value is not preserved, inputs exceed outputs and fee by {} lovelace and {} assets",
+                balance.lovelace,
+                balance.assets.len()
+            ));
+        }
+    }
+
+    // fee and size, counting the signatures still to be attached
+
+    let signers: Vec<Hash<28>> = witness_set
+        .vkeywitness
+        .iter()
+        .flatten()
+        .map(|x| Hasher::<224>::hash(&x.vkey))
+        .collect();
+
+    let required = witness::required_signers(&tx, wallet_db).await?;
+
+    // which keys sign for a native script isn't known in advance, only the
+    // signatures required by everything else are counted
+    let missing = if signed {
+        0
+    } else {
+        required
+            .iter()
+            .filter(|(signer, sources)| !signers.contains(signer) && requires_key(sources))
+            .count() as u64
+    };
+
+    let size = built.tx_bytes.0.len() as u64
+        + match missing {
+            0 => 0,
+            n => fee::vkey_witnesses_size(n, witness_set.vkeywitness.is_some()),
+        };
+
+    if size > pparams.max_tx_size {
+        failures.push(miette!(
+            "transaction size of {size} bytes is over the maximum of {} bytes",
+            pparams.max_tx_size
+        ));
+    }
+
+    let (mem, steps) = witness_set
+        .redeemer
+        .iter()
+        .flatten()
+        .fold((0, 0), |(mem, steps), x| {
+            (mem + u64::from(x.ex_units.mem), steps + x.ex_units.steps)
+        });
+
+    let min_fee = fee::min_fee(pparams, size) + pparams.script_fee(mem, steps);
+
+    if body.fee < min_fee {
+        failures.push(miette!(
+            "fee of {} lovelace is below the minimum of {min_fee} lovelace",
+            body.fee
+        ));
+    }
+
+    if mem > pparams.max_tx_ex_units.0 || steps > pparams.max_tx_ex_units.1 {
+        failures.push(miette!(
+            "scripts use {mem} memory units and {steps} steps, over the maximum of {} and {}",
+            pparams.max_tx_ex_units.0,
+            pparams.max_tx_ex_units.1
+        ));
+    }
+
+    // validity interval
+
+    if let (Some(start), Some(ttl)) = (body.validity_interval_start, body.ttl) {
+        if start >= ttl {
+            failures.push(miette!(
+                "validity interval is empty, it starts at slot {start} and ends at slot {ttl}"
+            ));
+        }
+    }
+
+    match wallet_db.fetch_tip_slot().await.into_diagnostic()? {
+        Some(tip) => {
+            if let Some(ttl) = body.ttl.filter(|x| *x <= tip) {
+                failures.push(miette!(
+                    "transaction expired at slot {ttl}, the wallet is at slot {tip}"
+                ));
+            }
+
+            if let Some(start) = body.validity_interval_start.filter(|x| *x > tip) {
+                warn!(start, tip, "transaction is not valid yet");
+            }
+        }
+        None => warn!("wallet hasn't been updated, validity interval not checked against the tip"),
+    }
+
+    // collateral of transactions running scripts
+
+    if witness_set.redeemer.is_some() {
+        failures.extend(check_collateral(body, wallet_db, pparams).await?);
+    }
+
+    // witnesses
+
+    if signed {
+        for witness in witness_set.vkeywitness.iter().flatten() {
+            let checked = witness::VKeyWitness::new(&witness.vkey, &witness.signature)
+                .verify(&built.tx_hash.0);
+
+            if let Err(e) = checked {
+                failures.push(e);
+            }
+        }
+
+        // keys of native scripts are checked through the scripts
+        for (signer, sources) in required.iter() {
+            if requires_key(sources) && !signers.contains(signer) {
+                let sources: Vec<_> = sources.iter().map(|x| x.to_string()).collect();
+
+                failures.push(miette!(
+                    "missing signature of {signer}, required by {}",
+                    sources.join(", ")
+                ));
+            }
+        }
+
+        let validity = multisig::Validity::of(body);
+
+        for script in witness_set.native_script.iter().flatten() {
+            if !multisig::is_satisfied(script, &signers, &validity) {
+                failures.push(miette!(
+                    "native script {} is not satisfied by the attached signatures and validity interval",
+                    multisig::hash(script)
+                ));
+            }
+        }
+    }
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(ValidationFailed(failures).into())
+    }
+}
+
+/// Collateral inputs must be key locked and known, not too many, and the
+/// lovelace they provide after the collateral return must cover the fee by
+/// the collateral percentage
+async fn check_collateral(
+    body: &babbage::TransactionBody,
+    wallet_db: &WalletDB,
+    pparams: &ProtocolParams,
+) -> miette::Result<Vec<miette::ErrReport>> {
+    let inputs: Vec<_> = body.collateral.iter().flatten().collect();
+
+    if inputs.is_empty() {
+        return Ok(vec![miette!(
+            "transaction runs Plutus scripts but has no collateral"
+        )]);
+    }
+
+    let mut failures = vec![];
+
+    if inputs.len() as u64 > pparams.max_collateral_inputs {
+        failures.push(miette!(
+            "transaction has {} collateral inputs, over the maximum of {}",
+            inputs.len(),
+            pparams.max_collateral_inputs
+        ));
+    }
+
+    let mut provided = Some(Value::default());
+
+    for input in inputs {
+        match resolve(wallet_db, input).await? {
+            Some(utxo) if utxo.is_script() => {
+                failures.push(miette!(
+                    "collateral input {}#{} is locked by a script",
+                    input.transaction_id,
+                    input.index
+                ));
+            }
+            Some(utxo) => {
+                if let Some(x) = provided.as_mut() {
+                    x.add(&utxo_value(&utxo)?);
+                }
+            }
+            None => {
+                failures.push(miette!(
+                    "collateral input {}#{} is unknown to the wallet or already spent",
+                    input.transaction_id,
+                    input.index
+                ));
+                provided = None;
+            }
+        }
+    }
+
+    let Some(mut provided) = provided else {
+        return Ok(failures);
+    };
+
+    if let Some(output) = &body.collateral_return {
+        provided.sub(&output_value(output)?);
+    }
+
+    provided.normalize();
+
+    if !provided.assets.is_empty() {
+        failures.push(miette!(
+            "collateral holds native assets which the collateral return doesn't send back"
+        ));
+    }
+
+    let required = pparams.min_collateral(body.fee);
+
+    if provided.lovelace < required as i128 {
+        failures.push(miette!(
+            "collateral of {} lovelace is below the required {required} lovelace",
+            provided.lovelace
+        ));
+    }
+
+    if let Some(total) = body.total_collateral {
+        if total as i128 != provided.lovelace {
+            failures.push(miette!(
+                "total collateral is set to {total} lovelace but the collateral provides {}",
+                provided.lovelace
+            ));
+        }
+    }
+
+    Ok(failures)
+}
+
+/// Whether a key has to sign because of something else than being part of
+/// a native script
+fn requires_key(sources: &[SignerSource]) -> bool {
+    sources.iter().any(SignerSource::requires_key)
+}
+
+async fn resolve(
+    wallet_db: &WalletDB,
+    input: &babbage::TransactionInput,
+) -> miette::Result<Option<UtxoModel>> {
+    wallet_db
+        .resolve_utxo(input.transaction_id.as_ref(), input.index as i32)
+        .await
+        .into_diagnostic()
+        .context("resolving input")
+}
+
+/// Value held by a utxo of the wallet
+pub fn utxo_value(utxo: &UtxoModel) -> miette::Result<Value> {
+    let era = Era::try_from(utxo.era)
+        .into_diagnostic()
+        .context("parsing utxo era")?;
+
+    let output = MultiEraOutput::decode(era, &utxo.cbor)
+        .into_diagnostic()
+        .context("parsing utxo cbor")?;
+
+    Ok(Value::from_output(&output))
+}
+
+fn output_value(output: &babbage::TransactionOutput) -> miette::Result<Value> {
+    let cbor = minicbor::to_vec(output).into_diagnostic()?;
+
+    let output = MultiEraOutput::decode(Era::Babbage, &cbor)
+        .into_diagnostic()
+        .context("parsing transaction output")?;
+
+    Ok(Value::from_output(&output))
+}
+
+fn mint_value(body: &babbage::TransactionBody) -> Value {
+    let mut value = Value::default();
+
+    for (policy, assets) in body.mint.iter().flat_map(|x| x.iter()) {
+        for (name, amount) in assets.iter() {
+            value.add_asset(*policy, name.to_vec(), (*amount).into());
+        }
+    }
+
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use pallas::{
+        ledger::addresses::{
+            Address, Network, ShelleyAddress, ShelleyDelegationPart, ShelleyPaymentPart,
+        },
+        txbuilder::{Input, Output, StagingTransaction},
+    };
+
+    use super::*;
+    use crate::transaction::staging::StagingTx;
+
+    const UTXO_HASH: [u8; 32] = [9; 32];
+    const POLICY: [u8; 28] = [7; 28];
+
+    fn key_address() -> Address {
+        ShelleyAddress::new(
+            Network::Testnet,
+            ShelleyPaymentPart::key_hash([1; 28].into()),
+            ShelleyDelegationPart::Null,
+        )
+        .into()
+    }
+
+    fn script_address() -> Address {
+        ShelleyAddress::new(
+            Network::Testnet,
+            ShelleyPaymentPart::script_hash([5; 28].into()),
+            ShelleyDelegationPart::Null,
+        )
+        .into()
+    }
+
+    /// Wallet db holding, at indexes 0 to 3, 10 ada, 5 ada, 250000 lovelace
+    /// and a script locked 5 ada
+    async fn wallet_db(name: &str) -> (WalletDB, PathBuf) {
+        // start afresh if an earlier failing run left its db behind
+        let dir = std::env::temp_dir().join(format!("cardaminal_test_{name}"));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let wallet_db = WalletDB::open(name, &dir).await.unwrap();
+
+        let cbors: Vec<_> = [
+            (key_address(), 10_000_000),
+            (key_address(), 5_000_000),
+            (key_address(), 250_000),
+            (script_address(), 5_000_000),
+        ]
+        .into_iter()
+        .map(|(address, lovelace)| {
+            let output =
+                babbage::TransactionOutput::PostAlonzo(babbage::PostAlonzoTransactionOutput {
+                    address: address.to_vec().into(),
+                    value: babbage::Value::Coin(lovelace),
+                    datum_option: None,
+                    script_ref: None,
+                });
+
+            minicbor::to_vec(output).unwrap()
+        })
+        .collect();
+
+        let utxos = cbors
+            .iter()
+            .enumerate()
+            .map(|(index, cbor)| {
+                let output = MultiEraOutput::decode(Era::Babbage, cbor).unwrap();
+                (UTXO_HASH, index, output, 100, Era::Babbage)
+            })
+            .collect();
+
+        wallet_db.insert_utxos(utxos).await.unwrap();
+
+        (wallet_db, dir)
+    }
+
+    /// Spend the 10 ada utxo, sending back all but the fee
+    fn transfer(fee: u64) -> StagingTransaction {
+        StagingTransaction::new()
+            .input(Input::new(UTXO_HASH.into(), 0))
+            .output(Output::new(key_address(), 10_000_000 - fee))
+            .fee(fee)
+    }
+
+    /// Spend the 10 ada utxo minting a token with a Plutus policy
+    fn mint() -> StagingTransaction {
+        let output = Output::new(key_address(), 9_800_000)
+            .add_asset(POLICY.into(), b"token".to_vec(), 1)
+            .unwrap();
+
+        StagingTransaction::new()
+            .input(Input::new(UTXO_HASH.into(), 0))
+            .output(output)
+            .mint_asset(POLICY.into(), b"token".to_vec(), 1)
+            .unwrap()
+            .add_mint_redeemer(
+                POLICY.into(),
+                vec![0x80],
+                Some(staging::ex_units(1_000, 100_000)),
+            )
+            .fee(200_000)
+    }
+
+    async fn failures(
+        staging: StagingTx,
+        wallet_db: &WalletDB,
+        pparams: &ProtocolParams,
+    ) -> Vec<String> {
+        let built = staging.build().unwrap();
+
+        match validate(&built, wallet_db, pparams, false).await {
+            Ok(()) => vec![],
+            Err(e) => e
+                .downcast_ref::<ValidationFailed>()
+                .unwrap()
+                .0
+                .iter()
+                .map(|x| x.to_string())
+                .collect(),
+        }
+    }
+
+    fn staging(tx: StagingTransaction) -> StagingTx {
+        serde_json::from_value(serde_json::to_value(tx).unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn balance_fee_and_validity() {
+        let (wallet_db, dir) = wallet_db("validate_balance").await;
+        let pparams = ProtocolParams::default();
+
+        let found = failures(staging(transfer(200_000)), &wallet_db, &pparams).await;
+        assert!(found.is_empty(), "{found:?}");
+
+        // the output keeps more than what's left after the fee
+        let mut tx = transfer(50_000);
+        tx = tx
+            .remove_output(0)
+            .output(Output::new(key_address(), 9_990_000));
+
+        let found = failures(staging(tx), &wallet_db, &pparams).await;
+        assert_eq!(found.len(), 2, "{found:?}");
+        assert!(found[0].starts_with("value is not preserved"));
+        assert!(found[1].starts_with("fee of 50000 lovelace is below the minimum"));
+
+        let tx = transfer(200_000)
+            .valid_from_slot(300)
+            .invalid_from_slot(300);
+        let found = failures(staging(tx), &wallet_db, &pparams).await;
+        assert_eq!(found.len(), 1, "{found:?}");
+        assert!(found[0].starts_with("validity interval is empty"));
+
+        wallet_db.insert_recent_point(500, [0; 32]).await.unwrap();
+
+        let tx = transfer(200_000).invalid_from_slot(400);
+        let found = failures(staging(tx), &wallet_db, &pparams).await;
+        assert_eq!(found.len(), 1, "{found:?}");
+        assert!(found[0].starts_with("transaction expired at slot 400"));
+
+        drop(wallet_db);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn collateral() {
+        let (wallet_db, dir) = wallet_db("validate_collateral").await;
+        let pparams = ProtocolParams::default();
+
+        let found = failures(staging(mint()), &wallet_db, &pparams).await;
+        assert_eq!(
+            found,
+            vec!["transaction runs Plutus scripts but has no collateral"]
+        );
+
+        // 5 ada covering 150% of the fee, the rest returned
+        let mut tx = staging(
+            mint()
+                .collateral_input(Input::new(UTXO_HASH.into(), 1))
+                .collateral_output(Output::new(key_address(), 4_700_000)),
+        );
+        tx.total_collateral = Some(300_000);
+
+        let found = failures(tx, &wallet_db, &pparams).await;
+        assert!(found.is_empty(), "{found:?}");
+
+        // the declared total doesn't match what the collateral provides
+        let mut tx = staging(
+            mint()
+                .collateral_input(Input::new(UTXO_HASH.into(), 1))
+                .collateral_output(Output::new(key_address(), 4_800_000)),
+        );
+        tx.total_collateral = Some(300_000);
+
+        let found = failures(tx, &wallet_db, &pparams).await;
+        assert_eq!(found.len(), 2, "{found:?}");
+        assert!(found[0].starts_with("collateral of 200000 lovelace is below the required 300000"));
+        assert!(found[1].starts_with("total collateral is set to 300000 lovelace"));
+
+        let tx = mint()
+            .collateral_input(Input::new(UTXO_HASH.into(), 2))
+            .collateral_input(Input::new(UTXO_HASH.into(), 3));
+
+        let found = failures(staging(tx), &wallet_db, &pparams).await;
+        assert_eq!(found.len(), 2, "{found:?}");
+        assert!(found[0].contains("is locked by a script"));
+        assert!(found[1].starts_with("collateral of 250000 lovelace is below the required"));
+
+        drop(wallet_db);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+
+use miette::IntoDiagnostic;
+use pallas::{
+    crypto::hash::Hash,
+    ledger::{addresses::Address, traverse::MultiEraOutput},
+    txbuilder::{Output, StagingTransaction},
+};
+
+/// Signed multi-asset value used while balancing transactions, amounts can be
+/// negative or exceed u64 as they are the result of summing many values
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Value {
+    pub lovelace: i128,
+    pub assets: HashMap<(Hash<28>, Vec<u8>), i128>,
+}
+
+impl Value {
+    pub fn lovelace(amount: u64) -> Self {
+        Self {
+            lovelace: amount.into(),
+            assets: HashMap::new(),
+        }
+    }
+
+    pub fn from_output(output: &MultiEraOutput) -> Self {
+        let mut value = Self::lovelace(output.lovelace_amount());
+
+        for policy in output.non_ada_assets() {
+            for asset in policy.assets() {
+                value.add_asset(
+                    *policy.policy(),
+                    asset.name().to_vec(),
+                    asset.output_coin().unwrap_or_default().into(),
+                );
+            }
+        }
+
+        value
+    }
+
+    pub fn from_staging_output(output: &Output) -> Self {
+        let mut value = Self::lovelace(output.lovelace);
+
+        for (policy, assets) in output.assets.iter().flat_map(|x| x.iter()) {
+            for (name, amount) in assets.iter() {
+                value.add_asset(policy.0.into(), name.0.clone(), (*amount).into());
+            }
+        }
+
+        value
+    }
+
+    /// Value minted (positive) or burned (negative) by the transaction
+    pub fn from_mint(tx: &StagingTransaction) -> Self {
+        let mut value = Self::default();
+
+        for (policy, assets) in tx.mint.iter().flat_map(|x| x.iter()) {
+            for (name, amount) in assets.iter() {
+                value.add_asset(policy.0.into(), name.0.clone(), (*amount).into());
+            }
+        }
+
+        value
+    }
+
+    pub fn add_asset(&mut self, policy: Hash<28>, name: Vec<u8>, amount: i128) {
+        let entry = self.assets.entry((policy, name)).or_default();
+        *entry += amount;
+    }
+
+    pub fn add(&mut self, other: &Value) {
+        self.lovelace += other.lovelace;
+
+        for ((policy, name), amount) in other.assets.iter() {
+            self.add_asset(*policy, name.clone(), *amount);
+        }
+    }
+
+    pub fn sub(&mut self, other: &Value) {
+        self.lovelace -= other.lovelace;
+
+        for ((policy, name), amount) in other.assets.iter() {
+            self.add_asset(*policy, name.clone(), -amount);
+        }
+    }
+
+    pub fn asset_amount(&self, policy: &Hash<28>, name: &[u8]) -> i128 {
+        self.assets
+            .get(&(*policy, name.to_vec()))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Drop the assets whose amount nets to zero
+    pub fn normalize(&mut self) {
+        self.assets.retain(|_, amount| *amount != 0);
+    }
+
+    /// True if every amount in this value is at least the one in `other`
+    pub fn covers(&self, other: &Value) -> bool {
+        self.lovelace >= other.lovelace
+            && other
+                .assets
+                .iter()
+                .all(|((policy, name), amount)| self.asset_amount(policy, name) >= *amount)
+    }
+
+    pub fn is_non_negative(&self) -> bool {
+        self.lovelace >= 0 && self.assets.values().all(|x| *x >= 0)
+    }
+
+    /// Turn a non-negative value into a transaction output
+    pub fn to_output(&self, address: Address) -> miette::Result<Output> {
+        if !self.is_non_negative() {
+            miette::bail!("can't create an output holding a negative value");
+        }
+
+        let lovelace = self
+            .lovelace
+            .try_into()
+            .map_err(|_| miette::miette!("lovelace amount out of range"))?;
+
+        let mut output = Output::new(address, lovelace);
+
+        for ((policy, name), amount) in self.assets.iter().filter(|(_, x)| **x > 0) {
+            let amount = (*amount)
+                .try_into()
+                .map_err(|_| miette::miette!("asset amount out of range"))?;
+
+            output = output
+                .add_asset(*policy, name.clone(), amount)
+                .into_diagnostic()?;
+        }
+
+        Ok(output)
+    }
+}
@@ -0,0 +1,335 @@
+use std::{collections::BTreeMap, fmt};
+
+use miette::{bail, miette, Context, IntoDiagnostic};
+use pallas::{
+    codec::minicbor,
+    crypto::{
+        hash::{Hash, Hasher},
+        key::ed25519::{PublicKey, Signature},
+    },
+    ledger::{
+        addresses::{Address, StakePayload},
+        primitives::babbage,
+    },
+    txbuilder::BuiltTransaction,
+    wallet::PrivateKey,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::wallet::{dal::WalletDB, multisig};
+
+/// Signatures over a built transaction exchanged between the parties signing
+/// it, either as JSON or as the CBOR of a witness set holding only vkey
+/// witnesses (the format returned by CIP-30 wallets)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WitnessFile {
+    /// hash of the transaction body the witnesses sign, unknown when read
+    /// from a CBOR witness set
+    pub tx_hash: Option<Hash<32>>,
+    pub witnesses: Vec<VKeyWitness>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct VKeyWitness {
+    /// hex public key
+    pub vkey: String,
+    /// hex ed25519 signature of the transaction hash
+    pub signature: String,
+}
+
+impl VKeyWitness {
+    pub fn new(vkey: &[u8], signature: &[u8]) -> Self {
+        Self {
+            vkey: hex::encode(vkey),
+            signature: hex::encode(signature),
+        }
+    }
+
+    pub fn vkey_bytes(&self) -> miette::Result<[u8; 32]> {
+        hex::decode(&self.vkey)
+            .into_diagnostic()
+            .context("parsing public key hex")?
+            .try_into()
+            .map_err(|_| miette!("public key incorrect length"))
+    }
+
+    pub fn signature_bytes(&self) -> miette::Result<[u8; 64]> {
+        hex::decode(&self.signature)
+            .into_diagnostic()
+            .context("parsing signature hex")?
+            .try_into()
+            .map_err(|_| miette!("signature incorrect length"))
+    }
+
+    /// Fail unless the signature is valid for the given transaction hash
+    pub fn verify(&self, tx_hash: &[u8; 32]) -> miette::Result<()> {
+        verify_signature(tx_hash, &self.vkey_bytes()?, &self.signature_bytes()?)
+    }
+}
+
+/// Check an ed25519 signature of the transaction hash
+pub fn verify_signature(
+    tx_hash: &[u8; 32],
+    vkey: &[u8; 32],
+    signature: &[u8; 64],
+) -> miette::Result<()> {
+    let public_key = PublicKey::from(*vkey);
+
+    if !public_key.verify(tx_hash, &Signature::from(*signature)) {
+        bail!(
+            "signature by {} is not valid for transaction {}",
+            hex::encode(vkey),
+            hex::encode(tx_hash)
+        );
+    }
+
+    Ok(())
+}
+
+/// Attach a signature to a built transaction after checking it against the
+/// transaction hash, refusing keys which already signed it
+pub fn add_verified_signature(
+    built: BuiltTransaction,
+    vkey: [u8; 32],
+    signature: [u8; 64],
+) -> miette::Result<BuiltTransaction> {
+    if built
+        .signatures
+        .iter()
+        .flatten()
+        .any(|(public_key, _)| public_key.0 == vkey)
+    {
+        bail!("transaction already signed by {}", hex::encode(vkey));
+    }
+
+    verify_signature(&built.tx_hash.0, &vkey, &signature)?;
+
+    built
+        .add_signature(vkey.into(), signature)
+        .into_diagnostic()
+}
+
+/// Sign a built transaction with the given key, checking the result like any
+/// other added signature
+pub fn add_key_signature(
+    built: BuiltTransaction,
+    key: &PrivateKey,
+) -> miette::Result<BuiltTransaction> {
+    let vkey: [u8; 32] = key.public_key().as_ref().try_into().into_diagnostic()?;
+
+    let signature: [u8; 64] = key
+        .sign(built.tx_hash.0)
+        .as_ref()
+        .try_into()
+        .into_diagnostic()?;
+
+    add_verified_signature(built, vkey, signature)
+}
+
+/// Part of a transaction requiring the signature of a key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignerSource {
+    Input,
+    Collateral,
+    DisclosedSigner,
+    CertificateOrWithdrawal,
+    /// one of the keys of the attached native script with this hash
+    NativeScript(Hash<28>),
+}
+
+impl SignerSource {
+    /// Native scripts only need enough of their keys to sign, any other
+    /// source needs the key itself
+    pub fn requires_key(&self) -> bool {
+        !matches!(self, SignerSource::NativeScript(_))
+    }
+}
+
+impl fmt::Display for SignerSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignerSource::Input => write!(f, "input"),
+            SignerSource::Collateral => write!(f, "collateral"),
+            SignerSource::DisclosedSigner => write!(f, "disclosed signer"),
+            SignerSource::CertificateOrWithdrawal => write!(f, "certificate or withdrawal"),
+            SignerSource::NativeScript(x) => write!(f, "native script {x}"),
+        }
+    }
+}
+
+/// Key hashes whose signatures a built transaction needs, each with the
+/// parts of the transaction requiring it: wallet inputs and collateral not
+/// locked by a script, disclosed signers, certificates, withdrawals and the
+/// keys of the attached native scripts (which may need only some of them).
+/// Fee estimation, validation and inspection all derive signers from here.
+pub async fn required_signers(
+    tx: &babbage::Tx,
+    wallet_db: &WalletDB,
+) -> miette::Result<BTreeMap<Hash<28>, Vec<SignerSource>>> {
+    let body = &tx.transaction_body;
+
+    let mut signers: BTreeMap<Hash<28>, Vec<SignerSource>> = BTreeMap::new();
+
+    let mut require = |hash: Hash<28>, source: SignerSource| {
+        let sources = signers.entry(hash).or_default();
+
+        if !sources.contains(&source) {
+            sources.push(source);
+        }
+    };
+
+    let inputs = body.inputs.iter().map(|x| (x, SignerSource::Input)).chain(
+        body.collateral
+            .iter()
+            .flatten()
+            .map(|x| (x, SignerSource::Collateral)),
+    );
+
+    for (input, source) in inputs {
+        let resolved = wallet_db
+            .resolve_utxo(input.transaction_id.as_ref(), input.index as i32)
+            .await
+            .into_diagnostic()
+            .context("resolving input")?;
+
+        if let Some(utxo) = resolved.filter(|x| !x.is_script()) {
+            let hash: [u8; 28] = utxo
+                .payment_cred
+                .try_into()
+                .map_err(|_| miette!("malformed payment credential in wallet db"))?;
+
+            require(hash.into(), source);
+        }
+    }
+
+    for signer in body.required_signers.iter().flatten() {
+        require(*signer, SignerSource::DisclosedSigner);
+    }
+
+    for signer in required_stake_signers(body) {
+        require(signer, SignerSource::CertificateOrWithdrawal);
+    }
+
+    for script in tx.transaction_witness_set.native_script.iter().flatten() {
+        for key in multisig::key_hashes(script) {
+            require(key, SignerSource::NativeScript(multisig::hash(script)));
+        }
+    }
+
+    Ok(signers)
+}
+
+/// Whether the signatures attached to a built transaction cover every key it
+/// requires and satisfy all of its native scripts
+pub async fn is_fully_signed(tx: &babbage::Tx, wallet_db: &WalletDB) -> miette::Result<bool> {
+    let witness_set = &tx.transaction_witness_set;
+
+    let signed: Vec<Hash<28>> = witness_set
+        .vkeywitness
+        .iter()
+        .flatten()
+        .map(|x| Hasher::<224>::hash(&x.vkey))
+        .collect();
+
+    let keys_signed = required_signers(tx, wallet_db)
+        .await?
+        .iter()
+        .filter(|(_, sources)| sources.iter().any(SignerSource::requires_key))
+        .all(|(signer, _)| signed.contains(signer));
+
+    let validity = multisig::Validity::of(&tx.transaction_body);
+
+    let scripts_satisfied = witness_set
+        .native_script
+        .iter()
+        .flatten()
+        .all(|x| multisig::is_satisfied(x, &signed, &validity));
+
+    Ok(keys_signed && scripts_satisfied)
+}
+
+/// Stake key hashes which have to witness the certificates and withdrawals
+/// of a transaction body, stake registrations and script credentials don't
+/// need one
+pub fn required_stake_signers(body: &babbage::TransactionBody) -> Vec<Hash<28>> {
+    let mut signers = vec![];
+
+    for cert in body.certificates.iter().flatten() {
+        match cert {
+            babbage::Certificate::StakeDeregistration(babbage::StakeCredential::AddrKeyhash(x))
+            | babbage::Certificate::StakeDelegation(babbage::StakeCredential::AddrKeyhash(x), _) => {
+                signers.push(*x)
+            }
+            _ => (),
+        }
+    }
+
+    for (account, _) in body.withdrawals.iter().flat_map(|x| x.iter()) {
+        if let Ok(Address::Stake(x)) = Address::from_bytes(account) {
+            if let StakePayload::Stake(hash) = x.payload() {
+                signers.push(*hash);
+            }
+        }
+    }
+
+    signers.sort();
+    signers.dedup();
+
+    signers
+}
+
+impl WitnessFile {
+    pub fn to_cbor(&self) -> miette::Result<Vec<u8>> {
+        let vkeywitness = self
+            .witnesses
+            .iter()
+            .map(|x| {
+                Ok(babbage::VKeyWitness {
+                    vkey: x.vkey_bytes()?.to_vec().into(),
+                    signature: x.signature_bytes()?.to_vec().into(),
+                })
+            })
+            .collect::<miette::Result<_>>()?;
+
+        let witness_set = babbage::WitnessSet {
+            vkeywitness: Some(vkeywitness),
+            native_script: None,
+            bootstrap_witness: None,
+            plutus_v1_script: None,
+            plutus_data: None,
+            redeemer: None,
+            plutus_v2_script: None,
+        };
+
+        minicbor::to_vec(witness_set).into_diagnostic()
+    }
+
+    /// Read a witness file in any of the supported formats: JSON, raw CBOR
+    /// witness set or its hex
+    pub fn parse(bytes: &[u8]) -> miette::Result<Self> {
+        if let Ok(file) = serde_json::from_slice(bytes) {
+            return Ok(file);
+        }
+
+        let cbor = match std::str::from_utf8(bytes).map(|x| hex::decode(x.trim())) {
+            Ok(Ok(decoded)) => decoded,
+            _ => bytes.to_vec(),
+        };
+
+        let witness_set: babbage::WitnessSet = minicbor::decode(&cbor)
+            .into_diagnostic()
+            .context("witness file is neither JSON nor a CBOR witness set")?;
+
+        let witnesses = witness_set
+            .vkeywitness
+            .iter()
+            .flatten()
+            .map(|x| VKeyWitness::new(&x.vkey, &x.signature))
+            .collect();
+
+        Ok(Self {
+            tx_hash: None,
+            witnesses,
+        })
+    }
+}
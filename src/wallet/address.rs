@@ -1,8 +1,13 @@
 use clap::Parser;
-use miette::bail;
-use tracing::instrument;
+use miette::{bail, miette, IntoDiagnostic};
+use pallas::{crypto::hash::Hash, ledger::addresses::ShelleyPaymentPart};
+use tracing::{info, instrument};
 
-use super::config;
+use super::{
+    config::{self, Addresses},
+    dal::WalletDB,
+    discovery, hd,
+};
 
 #[derive(Parser)]
 pub struct Args {
@@ -12,22 +17,101 @@ pub struct Args {
     /// Show testnet address instead of mainnet
     #[arg(long, short, action)]
     testnet: bool,
+
+    /// Show the enterprise address (without stake key) instead of the base
+    /// address
+    #[arg(long, short, action)]
+    enterprise: bool,
+
+    /// Hand out a fresh receive address (HD wallets only)
+    #[arg(long, short, action)]
+    new: bool,
+
+    /// wallet password, only needed the first time addresses are derived for
+    /// wallets created before address discovery was supported
+    #[arg(long, short)]
+    password: Option<String>,
 }
 
 #[instrument("info", skip_all, fields(name=args.name))]
 pub async fn run(args: Args, ctx: &crate::Context) -> miette::Result<()> {
     let cfg = config::Wallet::load_config(&ctx.dirs.root_dir, &args.name)?;
 
-    let cfg = match cfg {
+    let mut cfg = match cfg {
         Some(x) => x,
         None => bail!("wallet doesn't exist"),
     };
 
-    if args.testnet {
-        println!("{}", cfg.addresses.testnet);
+    let network_id = if args.testnet { 0 } else { 1 };
+
+    let addresses = if args.new {
+        let pkh = new_address(&mut cfg, args.password.as_ref(), ctx).await?;
+
+        Addresses::new(
+            ShelleyPaymentPart::key_hash(pkh),
+            cfg.keys.delegation_part()?,
+        )?
+    } else {
+        cfg.addresses
+    };
+
+    if args.enterprise {
+        println!("{}", addresses.enterprise_for_network(network_id));
     } else {
-        println!("{}", cfg.addresses.mainnet);
+        println!("{}", addresses.for_network(network_id));
     }
 
     Ok(())
 }
+
+/// Issue the next unused external address of an HD wallet
+async fn new_address(
+    cfg: &mut config::Wallet,
+    password: Option<&String>,
+    ctx: &crate::Context,
+) -> miette::Result<Hash<28>> {
+    if cfg.keys.account_public.is_none() {
+        let root_encrypted = match &cfg.keys.root_encrypted {
+            Some(x) => x,
+            None => bail!("only wallets created from a mnemonic can derive new addresses"),
+        };
+
+        let password = password.ok_or(miette!("password is required to derive addresses"))?;
+
+        let root = hd::decrypt_root_key(
+            password,
+            hex::decode(root_encrypted).map_err(|_| miette!("malformed encrypted root key"))?,
+        )?;
+
+        cfg.keys.account_public = Some(hex::encode(hd::account_public_key(&root).as_bytes()));
+        cfg.save_config(&ctx.dirs.root_dir)?;
+
+        info!("account public key stored");
+    }
+
+    let account = discovery::account_public_key(cfg)?.ok_or(miette!("missing account key"))?;
+
+    let wallet_db = WalletDB::open(
+        &cfg.name,
+        &config::Wallet::dir(&ctx.dirs.root_dir, &cfg.name),
+    )
+    .await
+    .into_diagnostic()?;
+
+    discovery::ensure_gap(&wallet_db, &account).await?;
+
+    let issued = wallet_db
+        .issue_wallet_address(hd::ROLE_EXTERNAL)
+        .await
+        .into_diagnostic()?
+        .ok_or(miette!(
+            "all addresses within the gap limit were handed out, wait for some to be used"
+        ))?;
+
+    let hash: [u8; 28] = issued
+        .payment_cred
+        .try_into()
+        .map_err(|_| miette!("malformed payment credential in wallet db"))?;
+
+    Ok(hash.into())
+}
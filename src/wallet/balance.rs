@@ -9,7 +9,7 @@ use tracing::instrument;
 use crate::{utils::OutputFormatter, OutputFormat};
 
 use super::{
-    config::{BalanceView, Wallet},
+    config::{BalanceView, StakeView, Wallet},
     dal::WalletDB,
 };
 
@@ -65,7 +65,13 @@ pub async fn run(args: Args, ctx: &crate::Context) -> miette::Result<()> {
         }
     }
 
-    let balance = BalanceView::new(lovelace, tokens.into_iter().collect());
+    let mut balance = BalanceView::new(lovelace, tokens.into_iter().collect());
+
+    if wallet.keys.stake_key_hash.is_some() {
+        let events = wallet_db.fetch_stake_events().await.into_diagnostic()?;
+
+        balance = balance.with_stake(StakeView::from_events(&events));
+    }
 
     match ctx.output_format {
         OutputFormat::Json => balance.to_json(),
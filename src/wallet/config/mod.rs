@@ -5,25 +5,140 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use chrono::{DateTime, Local};
+use bech32::ToBase32;
+use chrono::{DateTime, Local, Utc};
 use comfy_table::Table;
 use miette::{Context, IntoDiagnostic};
-use pallas::ledger::traverse::{Era, MultiEraOutput};
+use pallas::ledger::{
+    addresses::{Network, ShelleyAddress, ShelleyDelegationPart, ShelleyPaymentPart},
+    primitives::{
+        alonzo::NativeScript,
+        babbage::{MintedDatumOption, MintedScriptRef},
+    },
+    traverse::{ComputeHash, Era, MultiEraOutput, OriginalHash},
+};
 use serde::{Deserialize, Serialize};
 
-use super::dal::entities::utxo::Model as UtxoModel;
+use super::{
+    dal::entities::{
+        history_asset::Model as HistoryAssetModel,
+        stake::{Kind as StakeKind, Model as StakeModel},
+        tx_history::Model as TxHistoryModel,
+        utxo::Model as UtxoModel,
+    },
+    multisig,
+};
 use crate::utils::{deserialize_date, serialize_date, OutputFormatter};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Addresses {
     pub mainnet: String,
     pub testnet: String,
+    /// addresses without delegation part, only set when the main addresses
+    /// carry a stake key
+    pub enterprise_mainnet: Option<String>,
+    pub enterprise_testnet: Option<String>,
+}
+
+impl Addresses {
+    /// Mainnet and testnet addresses for the given credentials, along with
+    /// their enterprise variants if there is a delegation part
+    pub fn new(
+        payment: ShelleyPaymentPart,
+        delegation: ShelleyDelegationPart,
+    ) -> miette::Result<Self> {
+        let bech32 = |network, delegation| {
+            ShelleyAddress::new(network, payment.clone(), delegation)
+                .to_bech32()
+                .into_diagnostic()
+        };
+
+        let (enterprise_mainnet, enterprise_testnet) = match delegation {
+            ShelleyDelegationPart::Null => (None, None),
+            _ => (
+                Some(bech32(Network::Mainnet, ShelleyDelegationPart::Null)?),
+                Some(bech32(Network::Testnet, ShelleyDelegationPart::Null)?),
+            ),
+        };
+
+        Ok(Self {
+            mainnet: bech32(Network::Mainnet, delegation.clone())?,
+            testnet: bech32(Network::Testnet, delegation)?,
+            enterprise_mainnet,
+            enterprise_testnet,
+        })
+    }
+
+    /// The bech32 address matching the given address network id
+    pub fn for_network(&self, network_id: u8) -> &str {
+        match network_id {
+            1 => &self.mainnet,
+            _ => &self.testnet,
+        }
+    }
+
+    /// The bech32 enterprise address matching the given address network id
+    pub fn enterprise_for_network(&self, network_id: u8) -> &str {
+        let enterprise = match network_id {
+            1 => &self.enterprise_mainnet,
+            _ => &self.enterprise_testnet,
+        };
+
+        enterprise
+            .as_deref()
+            .unwrap_or_else(|| self.for_network(network_id))
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Keys {
+    /// payment key hash, or the script hash for multisig wallets
     pub public_key_hash: String,
-    pub private_encrypted: String,
+    /// encrypted payment key, missing for watch-only wallets
+    pub private_encrypted: Option<String>,
+    /// encrypted BIP32 root key, only for wallets created from a mnemonic
+    pub root_encrypted: Option<String>,
+    /// extended public key of the first account, used to derive additional
+    /// addresses of HD wallets
+    pub account_public: Option<String>,
+    pub stake_key_hash: Option<String>,
+    pub stake_private_encrypted: Option<String>,
+    /// cbor hex of the native script locking the addresses of multisig
+    /// wallets
+    pub multisig_script: Option<String>,
+}
+
+impl Keys {
+    /// Watch-only wallets track addresses whose keys are held elsewhere
+    pub fn is_watch_only(&self) -> bool {
+        self.private_encrypted.is_none()
+    }
+
+    /// Native script of a multisig wallet
+    pub fn multisig_script(&self) -> miette::Result<Option<NativeScript>> {
+        self.multisig_script
+            .as_deref()
+            .map(|x| {
+                let bytes = hex::decode(x)
+                    .into_diagnostic()
+                    .context("parsing multisig script hex")?;
+
+                multisig::from_cbor(&bytes)
+            })
+            .transpose()
+    }
+
+    /// Delegation part of the wallet addresses, the stake key if the wallet
+    /// has one
+    pub fn delegation_part(&self) -> miette::Result<ShelleyDelegationPart> {
+        match &self.stake_key_hash {
+            Some(hash) => Ok(ShelleyDelegationPart::Key(
+                hash.parse()
+                    .map_err(|_| miette::miette!("invalid stake key hash"))?,
+            )),
+            None => Ok(ShelleyDelegationPart::Null),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -32,18 +147,9 @@ pub struct SubmitApi {
     pub headers: HashMap<String, String>,
 }
 
-const DEFAULT_SUBMIT_API_URL: &str =
+pub const DEFAULT_SUBMIT_API_URL: &str =
     "https://submitapi-preprod-api-peaceful-relation-132bd4.us1.demeter.run/api/submit/tx";
 
-impl Default for SubmitApi {
-    fn default() -> Self {
-        Self {
-            url: String::from(DEFAULT_SUBMIT_API_URL),
-            headers: HashMap::default(),
-        }
-    }
-}
-
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Wallet {
     pub version: String,
@@ -51,6 +157,9 @@ pub struct Wallet {
     pub keys: Keys,
     pub addresses: Addresses,
     pub chain: Option<String>,
+    /// hashes of the native or plutus scripts whose outputs are tracked
+    #[serde(default)]
+    pub scripts: Vec<String>,
     pub submit_api: Option<SubmitApi>,
 
     #[serde(serialize_with = "serialize_date")]
@@ -68,6 +177,7 @@ impl Wallet {
             keys,
             addresses,
             chain,
+            scripts: vec![],
             submit_api: None,
             created_on: Local::now(),
         }
@@ -133,10 +243,35 @@ impl OutputFormatter for Wallet {
             "Chain",
             &self.chain.as_deref().unwrap_or("not attached"),
         ]);
-        table.add_row(vec!["Public Key Hash", &self.keys.public_key_hash]);
+        if self.keys.multisig_script.is_some() {
+            table.add_row(vec!["Multisig Script Hash", &self.keys.public_key_hash]);
+        } else {
+            table.add_row(vec!["Public Key Hash", &self.keys.public_key_hash]);
+        }
+
+        if self.keys.is_watch_only() && self.keys.multisig_script.is_none() {
+            table.add_row(vec!["Watch Only", "yes"]);
+        }
+
+        if let Some(stake_key_hash) = &self.keys.stake_key_hash {
+            table.add_row(vec!["Stake Key Hash", stake_key_hash]);
+        }
+
         table.add_row(vec!["Address (mainnet)", &self.addresses.mainnet]);
         table.add_row(vec!["Address (testnet)", &self.addresses.testnet]);
 
+        if let Some(address) = &self.addresses.enterprise_mainnet {
+            table.add_row(vec!["Enterprise Address (mainnet)", address]);
+        }
+
+        if let Some(address) = &self.addresses.enterprise_testnet {
+            table.add_row(vec!["Enterprise Address (testnet)", address]);
+        }
+
+        if !self.scripts.is_empty() {
+            table.add_row(vec!["Watched Scripts", &self.scripts.join("\n")]);
+        }
+
         println!("{table}");
     }
 
@@ -176,7 +311,13 @@ pub struct UtxoView {
     pub tx_hash: String,
     pub txo_index: i32,
     pub lovelace: u64,
-    pub datum: bool,
+    /// hash of the script locking the output, if any
+    pub script: Option<String>,
+    /// datum hash or inline datum cbor, prefixed by its kind
+    pub datum: Option<String>,
+    /// hash of the reference script carried by the output, prefixed by its
+    /// kind
+    pub reference_script: Option<String>,
     pub tokens: Vec<(String, u64)>,
 }
 
@@ -184,7 +325,15 @@ impl OutputFormatter for Vec<UtxoView> {
     fn to_table(&self) {
         let mut table = Table::new();
 
-        table.set_header(vec!["tx hash", "txo index", "lovelace", "datum", "tokens"]);
+        table.set_header(vec![
+            "tx hash",
+            "txo index",
+            "lovelace",
+            "script",
+            "datum",
+            "reference script",
+            "tokens",
+        ]);
 
         for utxo in self {
             let tokens = utxo
@@ -198,7 +347,9 @@ impl OutputFormatter for Vec<UtxoView> {
                 &utxo.tx_hash,
                 &utxo.txo_index.to_string(),
                 &utxo.lovelace.to_string(),
-                &utxo.datum.to_string(),
+                utxo.script.as_deref().unwrap_or_default(),
+                utxo.datum.as_deref().unwrap_or_default(),
+                utxo.reference_script.as_deref().unwrap_or_default(),
                 &tokens,
             ]);
         }
@@ -222,11 +373,21 @@ impl TryFrom<UtxoModel> for UtxoView {
 
         let output = MultiEraOutput::decode(era, &value.cbor).into_diagnostic()?;
 
+        let script = value.is_script().then(|| hex::encode(&value.payment_cred));
+
         let tx_hash = hex::encode(value.tx_hash);
         let txo_index = value.txo_index;
 
         let lovelace = output.lovelace_amount();
-        let datum: bool = output.datum().is_some();
+        let datum = output.datum().map(|x| match x {
+            MintedDatumOption::Hash(h) => format!("hash {h}"),
+            MintedDatumOption::Data(d) => format!("inline {}", hex::encode(d.0.raw_cbor())),
+        });
+        let reference_script = output.script_ref().map(|x| match x {
+            MintedScriptRef::NativeScript(s) => format!("native {}", s.original_hash()),
+            MintedScriptRef::PlutusV1Script(s) => format!("plutus-v1 {}", s.compute_hash()),
+            MintedScriptRef::PlutusV2Script(s) => format!("plutus-v2 {}", s.compute_hash()),
+        });
         let tokens: Vec<(String, u64)> = output
             .non_ada_assets()
             .iter()
@@ -247,7 +408,9 @@ impl TryFrom<UtxoModel> for UtxoView {
             tx_hash,
             txo_index,
             lovelace,
+            script,
             datum,
+            reference_script,
             tokens,
         };
 
@@ -255,15 +418,63 @@ impl TryFrom<UtxoModel> for UtxoView {
     }
 }
 
+/// Wallet config shown by `wallet info`, with the state of its stake key
+#[derive(Debug, Serialize)]
+pub struct WalletInfoView {
+    #[serde(flatten)]
+    pub wallet: Wallet,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stake: Option<StakeView>,
+}
+
+impl WalletInfoView {
+    pub fn new(wallet: Wallet) -> Self {
+        Self {
+            wallet,
+            stake: None,
+        }
+    }
+
+    pub fn with_stake(mut self, stake: StakeView) -> Self {
+        self.stake = Some(stake);
+        self
+    }
+}
+
+impl OutputFormatter for WalletInfoView {
+    fn to_table(&self) {
+        self.wallet.to_table();
+
+        if let Some(stake) = &self.stake {
+            stake.to_table();
+        }
+    }
+
+    fn to_json(&self) {
+        let json = serde_json::to_string_pretty(self).unwrap();
+        println!("{json}");
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct BalanceView {
     pub lovelace: u64,
     pub tokens: Vec<(String, u64)>,
+    pub stake: Option<StakeView>,
 }
 
 impl BalanceView {
     pub fn new(lovelace: u64, tokens: Vec<(String, u64)>) -> Self {
-        Self { lovelace, tokens }
+        Self {
+            lovelace,
+            tokens,
+            stake: None,
+        }
+    }
+
+    pub fn with_stake(mut self, stake: StakeView) -> Self {
+        self.stake = Some(stake);
+        self
     }
 }
 
@@ -280,6 +491,148 @@ impl OutputFormatter for BalanceView {
         }
 
         println!("{table}");
+
+        if let Some(stake) = &self.stake {
+            stake.to_table();
+        }
+    }
+
+    fn to_json(&self) {
+        let json = serde_json::to_string_pretty(self).unwrap();
+        println!("{json}");
+    }
+}
+
+/// State of the wallet stake credential, folded from its stake events
+#[derive(Debug, Default, Serialize)]
+pub struct StakeView {
+    pub registered: bool,
+    pub delegated_to: Option<String>,
+    pub withdrawn: u64,
+}
+
+impl StakeView {
+    /// Fold the stake events of the wallet, which must be in chain order
+    pub fn from_events(events: &[StakeModel]) -> Self {
+        let mut view = Self::default();
+
+        for event in events {
+            match event.kind {
+                StakeKind::Registration => view.registered = true,
+                StakeKind::Deregistration => {
+                    view.registered = false;
+                    view.delegated_to = None;
+                }
+                StakeKind::Delegation => {
+                    view.delegated_to = event.pool_id.as_deref().map(pool_bech32);
+                }
+                StakeKind::Withdrawal => {
+                    view.withdrawn += event.amount.unwrap_or_default() as u64;
+                }
+            }
+        }
+
+        view
+    }
+}
+
+fn pool_bech32(pool_id: &[u8]) -> String {
+    bech32::encode("pool", pool_id.to_base32(), bech32::Variant::Bech32)
+        .unwrap_or_else(|_| hex::encode(pool_id))
+}
+
+impl OutputFormatter for StakeView {
+    fn to_table(&self) {
+        let mut table = Table::new();
+
+        table.set_header(vec!["stake", "value"]);
+
+        table.add_row(vec!["registered".to_string(), self.registered.to_string()]);
+        table.add_row(vec![
+            "delegated to".to_string(),
+            self.delegated_to
+                .clone()
+                .unwrap_or_else(|| "not delegated".to_string()),
+        ]);
+        table.add_row(vec![
+            "rewards withdrawn".to_string(),
+            self.withdrawn.to_string(),
+        ]);
+
+        println!("{table}");
+    }
+
+    fn to_json(&self) {
+        let json = serde_json::to_string_pretty(self).unwrap();
+        println!("{json}");
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct HistoryView {
+    pub slot: u64,
+    pub tx_hash: String,
+    pub block_index: i32,
+    pub lovelace_delta: i128,
+    pub assets: Vec<(String, i128)>,
+    pub block_time: Option<DateTime<Utc>>,
+}
+
+impl HistoryView {
+    fn ada_delta(&self) -> String {
+        let sign = if self.lovelace_delta < 0 { "-" } else { "+" };
+        let abs = self.lovelace_delta.unsigned_abs();
+
+        format!("{sign}{}.{:06} ADA", abs / 1_000_000, abs % 1_000_000)
+    }
+
+    /// Attach the per-asset deltas stored for the transaction
+    pub fn with_assets(mut self, assets: Vec<HistoryAssetModel>) -> miette::Result<Self> {
+        for asset in assets {
+            let delta_bytes: [u8; 16] = asset
+                .delta
+                .try_into()
+                .map_err(|_| miette::miette!("malformed asset delta"))?;
+
+            let name = String::from_utf8(asset.asset_name.clone())
+                .ok()
+                .filter(|n| n.chars().all(|c| c.is_ascii_graphic()))
+                .unwrap_or_else(|| hex::encode(&asset.asset_name));
+
+            let key = format!("{}:{}", hex::encode(asset.policy_id), name);
+
+            self.assets.push((key, i128::from_be_bytes(delta_bytes)));
+        }
+
+        Ok(self)
+    }
+}
+
+impl OutputFormatter for Vec<HistoryView> {
+    fn to_table(&self) {
+        let mut table = Table::new();
+
+        table.set_header(vec!["slot", "tx hash", "idx", "change", "tokens", "time"]);
+
+        for entry in self {
+            let tokens = entry
+                .assets
+                .iter()
+                .map(|(asset, delta)| format!("{delta:+} {asset}"))
+                .collect::<Vec<String>>()
+                .join("\n");
+
+            table.add_row(vec![
+                entry.slot.to_string(),
+                entry.tx_hash.clone(),
+                entry.block_index.to_string(),
+                entry.ada_delta(),
+                tokens,
+                entry.block_time.map(|t| t.to_rfc3339()).unwrap_or_default(),
+            ]);
+        }
+
+        println!("{table}");
     }
 
     fn to_json(&self) {
@@ -287,3 +640,25 @@ impl OutputFormatter for BalanceView {
         println!("{json}");
     }
 }
+
+impl TryFrom<TxHistoryModel> for HistoryView {
+    type Error = miette::ErrReport;
+
+    fn try_from(value: TxHistoryModel) -> Result<Self, Self::Error> {
+        let delta_bytes: [u8; 16] = value
+            .balance_delta
+            .try_into()
+            .map_err(|_| miette::miette!("malformed balance delta"))?;
+
+        let history_view = HistoryView {
+            slot: value.slot as u64,
+            tx_hash: hex::encode(value.tx_hash),
+            block_index: value.block_index,
+            lovelace_delta: i128::from_be_bytes(delta_bytes),
+            assets: vec![],
+            block_time: None,
+        };
+
+        Ok(history_view)
+    }
+}
@@ -1,20 +1,25 @@
-use std::fs;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 
 use clap::Parser;
-use miette::{bail, IntoDiagnostic};
+use miette::{bail, Context, IntoDiagnostic};
 use pallas::{
-    crypto::key::ed25519::SecretKey,
+    crypto::{hash::Hash, key::ed25519::SecretKey},
     ledger::{
-        addresses::{Network, ShelleyAddress, ShelleyDelegationPart, ShelleyPaymentPart},
+        addresses::{Address, ShelleyDelegationPart, ShelleyPaymentPart},
         traverse::ComputeHash,
     },
-    wallet::wrapper,
+    wallet::{hd::Bip32PrivateKey, wrapper},
 };
 use rand::rngs::OsRng;
-use tracing::{info, instrument};
+use tracing::{info, instrument, warn};
 
 use crate::{chain, wallet};
 
+use super::{hd, multisig};
+
 pub fn gather_inputs(args: &mut Args) -> miette::Result<()> {
     let password = inquire::Password::new("password:")
         .with_help_message("the spending password of your wallet")
@@ -43,22 +48,51 @@ pub struct Args {
     /// use interactive mode
     #[arg(long, short, action)]
     interactive: bool,
+
+    /// derive the keys from a new BIP39 mnemonic with this amount of words
+    #[arg(long, num_args = 0..=1, default_missing_value = "24", value_parser = ["12", "15", "24"])]
+    mnemonic: Option<String>,
+
+    /// create a wallet without private keys, which only tracks the given
+    /// address or extended public key
+    #[arg(long, action, conflicts_with = "mnemonic")]
+    watch_only: bool,
+
+    /// bech32 address to track (watch-only wallets)
+    #[arg(long, requires = "watch_only", conflicts_with = "xpub")]
+    address: Option<String>,
+
+    /// account extended public key to track, as hex or bech32 (watch-only
+    /// wallets)
+    #[arg(long, requires = "watch_only")]
+    xpub: Option<String>,
+
+    /// create a multisig wallet locked by the native script in this JSON
+    /// file (cardano-cli format: sig, all, any, atLeast, after, before)
+    #[arg(long, conflicts_with_all = ["mnemonic", "watch_only"])]
+    multisig: Option<PathBuf>,
 }
 
 #[instrument("create", skip_all)]
 pub async fn run(args: Args, ctx: &crate::Context) -> miette::Result<()> {
     let mut args = args;
 
-    let wallet_slug = slug::slugify(&args.name);
-    let wallet_path = wallet::config::Wallet::dir(&ctx.dirs.root_dir, &wallet_slug);
-    if wallet_path.exists() {
-        bail!("wallet already exists")
+    check_new_wallet(ctx, &args.name, args.chain.as_deref())?;
+
+    if let Some(path) = &args.multisig {
+        let key_data = multisig_keys(path)?;
+
+        return init_wallet(ctx, args.name, args.chain, key_data).await;
     }
 
-    if args.chain.is_some()
-        && !chain::config::Chain::dir(&ctx.dirs.root_dir, args.chain.as_ref().unwrap()).exists()
-    {
-        bail!("chain doesn't exist")
+    if args.watch_only {
+        let key_data = match (&args.address, &args.xpub) {
+            (Some(address), None) => address_keys(address)?,
+            (None, Some(xpub)) => xpub_keys(xpub)?,
+            _ => bail!("watch-only wallets require either --address or --xpub"),
+        };
+
+        return init_wallet(ctx, args.name, args.chain, key_data).await;
     }
 
     if args.interactive {
@@ -70,52 +104,209 @@ pub async fn run(args: Args, ctx: &crate::Context) -> miette::Result<()> {
         None => bail!("password is required"),
     };
 
-    let wallet_slug = slug::slugify(&args.name);
+    let key_data = match &args.mnemonic {
+        Some(words) => {
+            let mnemonic = hd::generate_mnemonic(words.parse().into_diagnostic()?)?;
+
+            warn!("write down the mnemonic below, it's the only way to restore the wallet");
+            println!("{mnemonic}");
+
+            hd_keys(&hd::root_from_mnemonic(&mnemonic)?, password)
+        }
+        None => random_keys(password),
+    };
+
+    init_wallet(ctx, args.name, args.chain, key_data).await
+}
+
+/// Keys for a wallet holding a single random private key
+fn random_keys(password: &String) -> wallet::config::Keys {
+    let priv_key = SecretKey::new(OsRng);
+    let stake_key = SecretKey::new(OsRng);
+
+    let pkh = priv_key.public_key().compute_hash();
+    let stake_hash = stake_key.public_key().compute_hash();
+
+    let encrypted_priv_key = wrapper::encrypt_private_key(OsRng, priv_key.into(), password);
+    let encrypted_stake_key = wrapper::encrypt_private_key(OsRng, stake_key.into(), password);
+
+    wallet::config::Keys {
+        public_key_hash: hex::encode(pkh),
+        private_encrypted: Some(hex::encode(encrypted_priv_key)),
+        root_encrypted: None,
+        account_public: None,
+        stake_key_hash: Some(hex::encode(stake_hash)),
+        stake_private_encrypted: Some(hex::encode(encrypted_stake_key)),
+        multisig_script: None,
+    }
+}
+
+/// Keys for an HD wallet, using the first external and stake keys of the
+/// first account (CIP-1852)
+pub fn hd_keys(root: &Bip32PrivateKey, password: &String) -> wallet::config::Keys {
+    let account = hd::derive_account(root, 0);
+    let payment = hd::derive_key(&account, hd::ROLE_EXTERNAL, 0).to_ed25519_private_key();
+    let stake = hd::derive_key(&account, hd::ROLE_STAKE, 0).to_ed25519_private_key();
+
+    let pkh = payment.public_key().compute_hash();
+    let stake_hash = stake.public_key().compute_hash();
+
+    let encrypted_priv_key = wrapper::encrypt_private_key(OsRng, payment, password);
+    let encrypted_stake_key = wrapper::encrypt_private_key(OsRng, stake, password);
+    let encrypted_root_key = hd::encrypt_root_key(root, password);
+
+    wallet::config::Keys {
+        public_key_hash: hex::encode(pkh),
+        private_encrypted: Some(hex::encode(encrypted_priv_key)),
+        root_encrypted: Some(hex::encode(encrypted_root_key)),
+        account_public: Some(hex::encode(hd::account_public_key(root).as_bytes())),
+        stake_key_hash: Some(hex::encode(stake_hash)),
+        stake_private_encrypted: Some(hex::encode(encrypted_stake_key)),
+        multisig_script: None,
+    }
+}
+
+/// Keys for a watch-only wallet tracking a single address
+fn address_keys(address: &str) -> miette::Result<wallet::config::Keys> {
+    let address = match Address::from_bech32(address).into_diagnostic()? {
+        Address::Shelley(x) => x,
+        _ => bail!("only shelley addresses can be watched"),
+    };
 
-    let wallet_path = ctx.dirs.root_dir.join("wallets").join(&wallet_slug);
+    let pkh = match address.payment() {
+        ShelleyPaymentPart::Key(h) => *h,
+        ShelleyPaymentPart::Script(_) => bail!("only addresses locked by a key can be watched"),
+    };
+
+    let stake_key_hash = match address.delegation() {
+        ShelleyDelegationPart::Key(h) => Some(hex::encode(h)),
+        ShelleyDelegationPart::Null => None,
+        _ => bail!("only addresses delegated to a stake key can be watched"),
+    };
+
+    Ok(wallet::config::Keys {
+        public_key_hash: hex::encode(pkh),
+        private_encrypted: None,
+        root_encrypted: None,
+        account_public: None,
+        stake_key_hash,
+        stake_private_encrypted: None,
+        multisig_script: None,
+    })
+}
+
+/// Keys for a watch-only HD wallet tracking the addresses derived from an
+/// account public key
+fn xpub_keys(xpub: &str) -> miette::Result<wallet::config::Keys> {
+    let account = hd::parse_account_public_key(xpub)?;
+
+    let pkh = hd::derive_key_hash(&account, hd::ROLE_EXTERNAL, 0)?;
+    let stake_hash = hd::derive_key_hash(&account, hd::ROLE_STAKE, 0)?;
+
+    Ok(wallet::config::Keys {
+        public_key_hash: hex::encode(pkh),
+        private_encrypted: None,
+        root_encrypted: None,
+        account_public: Some(hex::encode(account.as_bytes())),
+        stake_key_hash: Some(hex::encode(stake_hash)),
+        stake_private_encrypted: None,
+        multisig_script: None,
+    })
+}
+
+/// Keys for a multisig wallet, which holds no keys itself but tracks the
+/// address of a native script made of the participants key hashes
+fn multisig_keys(path: &Path) -> miette::Result<wallet::config::Keys> {
+    let json = fs::read_to_string(path)
+        .into_diagnostic()
+        .context("reading multisig script file")?;
+
+    let json: serde_json::Value = serde_json::from_str(&json)
+        .into_diagnostic()
+        .context("parsing multisig script json")?;
+
+    let script = multisig::from_json(&json)?;
+
+    if multisig::key_hashes(&script).is_empty() {
+        bail!("multisig script doesn't require any signature");
+    }
+
+    Ok(wallet::config::Keys {
+        public_key_hash: hex::encode(multisig::hash(&script)),
+        private_encrypted: None,
+        root_encrypted: None,
+        account_public: None,
+        stake_key_hash: None,
+        stake_private_encrypted: None,
+        multisig_script: Some(hex::encode(multisig::to_cbor(&script)?)),
+    })
+}
+
+/// Fail early if a wallet with this name can't be created
+pub fn check_new_wallet(
+    ctx: &crate::Context,
+    name: &str,
+    chain: Option<&str>,
+) -> miette::Result<()> {
+    let wallet_slug = slug::slugify(name);
+    let wallet_path = wallet::config::Wallet::dir(&ctx.dirs.root_dir, &wallet_slug);
     if wallet_path.exists() {
         bail!("wallet already exists")
     }
 
+    if let Some(chain) = chain {
+        if !chain::config::Chain::dir(&ctx.dirs.root_dir, chain).exists() {
+            bail!("chain doesn't exist")
+        }
+    }
+
+    Ok(())
+}
+
+/// Create the wallet dir, db and config for the given keys
+pub async fn init_wallet(
+    ctx: &crate::Context,
+    name: String,
+    chain: Option<String>,
+    key_data: wallet::config::Keys,
+) -> miette::Result<()> {
+    check_new_wallet(ctx, &name, chain.as_deref())?;
+
+    let wallet_slug = slug::slugify(&name);
+    let wallet_path = wallet::config::Wallet::dir(&ctx.dirs.root_dir, &wallet_slug);
+
     fs::create_dir_all(&wallet_path).into_diagnostic()?;
 
     // open wallet db
-    let db = wallet::dal::WalletDB::open(&args.name, &wallet_path)
+    let db = wallet::dal::WalletDB::open(&name, &wallet_path)
         .await
         .into_diagnostic()?;
 
     // create required tables in db
     db.migrate_up().await.into_diagnostic()?;
 
-    let priv_key = SecretKey::new(OsRng);
+    let pkh: Hash<28> = key_data
+        .public_key_hash
+        .parse()
+        .map_err(|_| miette::miette!("invalid public key hash"))?;
 
-    let pkh = priv_key.public_key().compute_hash();
-
-    let encrypted_priv_key = wrapper::encrypt_private_key(OsRng, priv_key.into(), password);
+    let is_multisig = key_data.multisig_script.is_some();
 
-    let key_data = wallet::config::Keys {
-        public_key_hash: hex::encode(pkh),
-        private_encrypted: hex::encode(encrypted_priv_key),
+    let payment = if is_multisig {
+        ShelleyPaymentPart::Script(pkh)
+    } else {
+        ShelleyPaymentPart::key_hash(pkh)
     };
 
-    let mainnet_address = ShelleyAddress::new(
-        Network::Mainnet,
-        ShelleyPaymentPart::key_hash(pkh.into()),
-        ShelleyDelegationPart::Null,
-    );
-
-    let testnet_address = ShelleyAddress::new(
-        Network::Testnet,
-        ShelleyPaymentPart::key_hash(pkh.into()),
-        ShelleyDelegationPart::Null,
-    );
-
-    let addresses = wallet::config::Addresses {
-        mainnet: mainnet_address.to_bech32().into_diagnostic()?,
-        testnet: testnet_address.to_bech32().into_diagnostic()?,
-    };
+    let addresses = wallet::config::Addresses::new(payment, key_data.delegation_part()?)?;
 
-    let wallet = wallet::config::Wallet::new(args.name, key_data, addresses, args.chain);
+    let mut wallet = wallet::config::Wallet::new(name, key_data, addresses, chain);
+
+    // outputs locked by the multisig script are tracked like any other
+    // registered script
+    if is_multisig {
+        wallet.scripts.push(hex::encode(pkh));
+    }
 
     wallet.save_config(&ctx.dirs.root_dir)?;
 
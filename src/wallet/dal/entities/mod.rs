@@ -2,8 +2,11 @@
 
 pub mod prelude;
 
+pub mod history_asset;
 pub mod protocol_parameters;
 pub mod recent_points;
+pub mod stake;
 pub mod transaction;
 pub mod tx_history;
 pub mod utxo;
+pub mod wallet_address;
@@ -1,7 +1,10 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.4
 
+pub use super::history_asset::Entity as HistoryAsset;
 pub use super::protocol_parameters::Entity as ProtocolParameters;
 pub use super::recent_points::Entity as RecentPoints;
+pub use super::stake::Entity as Stake;
 pub use super::transaction::Entity as Transaction;
 pub use super::tx_history::Entity as TxHistory;
 pub use super::utxo::Entity as Utxo;
+pub use super::wallet_address::Entity as WalletAddress;
@@ -1,5 +1,6 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.4
 
+use pallas::ledger::addresses::{Address, ShelleyPaymentPart};
 use sea_orm::entity::prelude::*;
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
@@ -24,3 +25,13 @@ pub struct Model {
 pub enum Relation {}
 
 impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    /// True if the output is locked by a script rather than a key
+    pub fn is_script(&self) -> bool {
+        matches!(
+            Address::from_bytes(&self.full_address),
+            Ok(Address::Shelley(x)) if matches!(x.payment(), ShelleyPaymentPart::Script(_))
+        )
+    }
+}
@@ -0,0 +1,71 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(HistoryAsset::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(HistoryAsset::Id)
+                            .unsigned()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(HistoryAsset::TxHash)
+                            .binary_len(32)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(HistoryAsset::Slot).big_unsigned().not_null())
+                    .col(
+                        ColumnDef::new(HistoryAsset::BlockIndex)
+                            .unsigned()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(HistoryAsset::PolicyId)
+                            .binary_len(28)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(HistoryAsset::AssetName).binary().not_null())
+                    .col(ColumnDef::new(HistoryAsset::Delta).binary().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-history-asset-policy")
+                    .table(HistoryAsset::Table)
+                    .col(HistoryAsset::PolicyId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(HistoryAsset::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum HistoryAsset {
+    Table,
+    Id,
+    TxHash,
+    Slot,
+    BlockIndex,
+    PolicyId,
+    AssetName,
+    Delta, // i128 big endian, can be negative or larger than u64
+}
@@ -0,0 +1,49 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Stake::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Stake::Id)
+                            .unsigned()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Stake::TxHash).binary_len(32).not_null())
+                    .col(ColumnDef::new(Stake::Slot).big_unsigned().not_null())
+                    .col(ColumnDef::new(Stake::BlockIndex).unsigned().not_null())
+                    .col(ColumnDef::new(Stake::Kind).string().not_null())
+                    .col(ColumnDef::new(Stake::PoolId).binary_len(28))
+                    .col(ColumnDef::new(Stake::Amount).big_unsigned())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Stake::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Stake {
+    Table,
+    Id,
+    TxHash,
+    Slot,
+    BlockIndex,
+    Kind,
+    PoolId, // only for delegations
+    Amount, // only for withdrawals
+}
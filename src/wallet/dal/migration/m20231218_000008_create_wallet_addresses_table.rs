@@ -0,0 +1,68 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(WalletAddress::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(WalletAddress::Id)
+                            .unsigned()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(WalletAddress::Role).unsigned().not_null())
+                    .col(
+                        ColumnDef::new(WalletAddress::AddressIndex)
+                            .unsigned()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WalletAddress::PaymentCred)
+                            .binary_len(28)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(WalletAddress::Used).boolean().not_null())
+                    .col(ColumnDef::new(WalletAddress::Issued).boolean().not_null())
+                    .col(ColumnDef::new(WalletAddress::UsedSlot).big_integer())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-wallet-address-payment-cred")
+                    .table(WalletAddress::Table)
+                    .col(WalletAddress::PaymentCred)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(WalletAddress::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum WalletAddress {
+    Table,
+    Id,
+    Role,
+    AddressIndex,
+    PaymentCred,
+    Used,     // received funds on chain
+    Issued,   // handed out by `wallet address --new` or as change
+    UsedSlot, // slot of the block which first used it, reset on rollback
+}
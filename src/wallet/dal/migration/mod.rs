@@ -5,6 +5,9 @@ mod m20231012_000002_create_history_table;
 mod m20231012_000003_create_intersects_table;
 mod m20231012_000004_create_pparams_table;
 mod m20231102_000005_create_transactions_table;
+mod m20231205_000006_create_history_assets_table;
+mod m20231211_000007_create_stake_table;
+mod m20231218_000008_create_wallet_addresses_table;
 
 pub struct Migrator;
 
@@ -17,6 +20,9 @@ impl MigratorTrait for Migrator {
             Box::new(m20231012_000003_create_intersects_table::Migration),
             Box::new(m20231012_000004_create_pparams_table::Migration),
             Box::new(m20231102_000005_create_transactions_table::Migration),
+            Box::new(m20231205_000006_create_history_assets_table::Migration),
+            Box::new(m20231211_000007_create_stake_table::Migration),
+            Box::new(m20231218_000008_create_wallet_addresses_table::Migration),
         ]
     }
 }
@@ -6,11 +6,18 @@ use std::path::{Path, PathBuf};
 use pallas::ledger::addresses::{Address, ShelleyPaymentPart};
 use pallas::ledger::traverse::{Era, MultiEraInput, MultiEraOutput};
 use sea_orm::entity::prelude::*;
+use sea_orm::sea_query::{Expr, Query};
 use sea_orm::{Condition, Database, Order, Paginator, QueryOrder, SelectModel, TransactionTrait};
 use sea_orm_migration::MigratorTrait;
 
-use self::entities::prelude::{ProtocolParameters, RecentPoints, Transaction, TxHistory, Utxo};
-use self::entities::{protocol_parameters, recent_points, transaction, tx_history, utxo};
+use self::entities::prelude::{
+    HistoryAsset, ProtocolParameters, RecentPoints, Stake, Transaction, TxHistory, Utxo,
+    WalletAddress,
+};
+use self::entities::{
+    history_asset, protocol_parameters, recent_points, stake, transaction, tx_history, utxo,
+    wallet_address,
+};
 use self::migration::Migrator;
 
 static DEFAULT_PAGE_SIZE: u64 = 20;
@@ -54,14 +61,17 @@ impl WalletDB {
 
             let address_bytes = address.to_vec();
 
+            // key hash or script hash, depending on what locks the output
             let payment_cred = match address {
                 Address::Shelley(s) => match s.payment() {
                     ShelleyPaymentPart::Key(h) => *h,
-                    ShelleyPaymentPart::Script(_) => {
-                        unimplemented!("cannot store script controlled utxos")
-                    }
+                    ShelleyPaymentPart::Script(h) => *h,
                 },
-                _ => unimplemented!("cannot store byron address controlled utxos"),
+                _ => {
+                    return Err(DbErr::Custom(
+                        "cannot store utxos without a shelley payment credential".into(),
+                    ))
+                }
             };
 
             let utxo_model = entities::utxo::ActiveModel {
@@ -186,6 +196,205 @@ impl WalletDB {
             .paginate(&self.conn, page_size.unwrap_or(DEFAULT_PAGE_SIZE))
     }
 
+    /// Paginate the transaction history, only including transactions which
+    /// moved assets of the specified policy
+    pub fn paginate_tx_history_for_policy(
+        &self,
+        policy: &[u8],
+        order: Order,
+        page_size: Option<u64>,
+    ) -> Paginator<'_, DatabaseConnection, SelectModel<tx_history::Model>> {
+        let involved_txs = Query::select()
+            .column(history_asset::Column::TxHash)
+            .from(HistoryAsset)
+            .and_where(history_asset::Column::PolicyId.eq(policy))
+            .to_owned();
+
+        TxHistory::find()
+            .filter(tx_history::Column::TxHash.in_subquery(involved_txs))
+            .order_by(tx_history::Column::Slot, order.clone())
+            .order_by(tx_history::Column::BlockIndex, order)
+            .paginate(&self.conn, page_size.unwrap_or(DEFAULT_PAGE_SIZE))
+    }
+
+    // Transaction History Assets
+
+    /// Store the per-asset balance deltas of a transaction as i128 big endian
+    pub async fn insert_history_assets(
+        &self,
+        tx_hash: [u8; 32],
+        slot: u64,
+        tx_block_index: u16,
+        deltas: Vec<(Vec<u8>, Vec<u8>, i128)>,
+    ) -> Result<(), DbErr> {
+        let txn = self.conn.begin().await?;
+
+        for (policy, name, delta) in deltas {
+            let asset_model = entities::history_asset::ActiveModel {
+                tx_hash: sea_orm::ActiveValue::Set(tx_hash.to_vec()),
+                slot: sea_orm::ActiveValue::Set(slot as i64),
+                block_index: sea_orm::ActiveValue::Set(tx_block_index.into()),
+                policy_id: sea_orm::ActiveValue::Set(policy),
+                asset_name: sea_orm::ActiveValue::Set(name),
+                delta: sea_orm::ActiveValue::Set(delta.to_be_bytes().to_vec()),
+                ..Default::default()
+            };
+
+            let _ = HistoryAsset::insert(asset_model).exec(&txn).await?;
+        }
+
+        txn.commit().await
+    }
+
+    pub async fn fetch_history_assets(
+        &self,
+        tx_hash: &[u8],
+    ) -> Result<Vec<history_asset::Model>, DbErr> {
+        HistoryAsset::find()
+            .filter(history_asset::Column::TxHash.eq(tx_hash))
+            .all(&self.conn)
+            .await
+    }
+
+    // Stake
+
+    /// Record a certificate or withdrawal involving the wallet stake
+    /// credential
+    pub async fn insert_stake_event(
+        &self,
+        tx_hash: [u8; 32],
+        slot: u64,
+        tx_block_index: u16,
+        kind: stake::Kind,
+        pool_id: Option<Vec<u8>>,
+        amount: Option<u64>,
+    ) -> Result<(), DbErr> {
+        let stake_model = entities::stake::ActiveModel {
+            tx_hash: sea_orm::ActiveValue::Set(tx_hash.to_vec()),
+            slot: sea_orm::ActiveValue::Set(slot as i64),
+            block_index: sea_orm::ActiveValue::Set(tx_block_index.into()),
+            kind: sea_orm::ActiveValue::Set(kind),
+            pool_id: sea_orm::ActiveValue::Set(pool_id),
+            amount: sea_orm::ActiveValue::Set(amount.map(|x| x as i64)),
+            ..Default::default()
+        };
+
+        let _ = Stake::insert(stake_model).exec(&self.conn).await?;
+
+        Ok(())
+    }
+
+    /// Fetch all the stake events of the wallet in chain order
+    pub async fn fetch_stake_events(&self) -> Result<Vec<stake::Model>, DbErr> {
+        Stake::find()
+            .order_by_asc(stake::Column::Slot)
+            .order_by_asc(stake::Column::BlockIndex)
+            .order_by_asc(stake::Column::Id)
+            .all(&self.conn)
+            .await
+    }
+
+    // Wallet Addresses
+
+    pub async fn insert_wallet_address(
+        &self,
+        role: u32,
+        index: u32,
+        payment_cred: [u8; 28],
+        issued: bool,
+    ) -> Result<(), DbErr> {
+        let address_model = entities::wallet_address::ActiveModel {
+            role: sea_orm::ActiveValue::Set(role as i32),
+            address_index: sea_orm::ActiveValue::Set(index as i32),
+            payment_cred: sea_orm::ActiveValue::Set(payment_cred.to_vec()),
+            used: sea_orm::ActiveValue::Set(false),
+            issued: sea_orm::ActiveValue::Set(issued),
+            used_slot: sea_orm::ActiveValue::Set(None),
+            ..Default::default()
+        };
+
+        let _ = WalletAddress::insert(address_model)
+            .exec(&self.conn)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn fetch_wallet_addresses(&self) -> Result<Vec<wallet_address::Model>, DbErr> {
+        WalletAddress::find()
+            .order_by_asc(wallet_address::Column::Role)
+            .order_by_asc(wallet_address::Column::AddressIndex)
+            .all(&self.conn)
+            .await
+    }
+
+    pub async fn fetch_wallet_address_by_cred(
+        &self,
+        payment_cred: &[u8],
+    ) -> Result<Option<wallet_address::Model>, DbErr> {
+        WalletAddress::find()
+            .filter(wallet_address::Column::PaymentCred.eq(payment_cred))
+            .one(&self.conn)
+            .await
+    }
+
+    /// Flag the wallet addresses with the given payment credentials as used,
+    /// returns true if any address wasn't used before
+    pub async fn mark_wallet_addresses_used(
+        &self,
+        payment_creds: Vec<Vec<u8>>,
+        slot: u64,
+    ) -> Result<bool, DbErr> {
+        let result = WalletAddress::update_many()
+            .col_expr(wallet_address::Column::Used, Expr::value(true))
+            .col_expr(wallet_address::Column::UsedSlot, Expr::value(slot as i64))
+            .filter(wallet_address::Column::PaymentCred.is_in(payment_creds))
+            .filter(wallet_address::Column::Used.eq(false))
+            .exec(&self.conn)
+            .await?;
+
+        Ok(result.rows_affected > 0)
+    }
+
+    /// Address of the role handed out before but not used on chain yet
+    pub async fn fetch_unused_issued_wallet_address(
+        &self,
+        role: u32,
+    ) -> Result<Option<wallet_address::Model>, DbErr> {
+        WalletAddress::find()
+            .filter(wallet_address::Column::Role.eq(role as i32))
+            .filter(wallet_address::Column::Used.eq(false))
+            .filter(wallet_address::Column::Issued.eq(true))
+            .order_by_asc(wallet_address::Column::AddressIndex)
+            .one(&self.conn)
+            .await
+    }
+
+    /// Hand out the lowest unused address of the role which wasn't handed out
+    /// before, flagging it as issued
+    pub async fn issue_wallet_address(
+        &self,
+        role: u32,
+    ) -> Result<Option<wallet_address::Model>, DbErr> {
+        let found = WalletAddress::find()
+            .filter(wallet_address::Column::Role.eq(role as i32))
+            .filter(wallet_address::Column::Used.eq(false))
+            .filter(wallet_address::Column::Issued.eq(false))
+            .order_by_asc(wallet_address::Column::AddressIndex)
+            .one(&self.conn)
+            .await?;
+
+        match found {
+            Some(model) => {
+                let mut active: wallet_address::ActiveModel = model.into();
+                active.issued = sea_orm::ActiveValue::Set(true);
+
+                Ok(Some(active.update(&self.conn).await?))
+            }
+            None => Ok(None),
+        }
+    }
+
     // Recent Points
 
     pub async fn insert_recent_point(&self, slot: u64, block_hash: [u8; 32]) -> Result<(), DbErr> {
@@ -210,6 +419,16 @@ impl WalletDB {
             .paginate(&self.conn, page_size.unwrap_or(DEFAULT_PAGE_SIZE))
     }
 
+    /// Slot of the most recent block the wallet was updated with
+    pub async fn fetch_tip_slot(&self) -> Result<Option<u64>, DbErr> {
+        let res = RecentPoints::find()
+            .order_by_desc(recent_points::Column::Slot)
+            .one(&self.conn)
+            .await?;
+
+        Ok(res.map(|r| r.slot as u64))
+    }
+
     pub async fn remove_recent_points_before_slot(&self, slot: u64) -> Result<(), DbErr> {
         let txn = self.conn.begin().await?;
 
@@ -247,15 +466,16 @@ impl WalletDB {
         Ok(())
     }
 
-    /// Fetch the CBOR of the most recent protocol parameters seen on-chain
-    pub async fn fetch_latest_protocol_parameters(&self) -> Result<Option<Vec<u8>>, DbErr> {
+    /// Fetch the CBOR of every protocol parameters update seen on-chain, in
+    /// the order they were applied
+    pub async fn fetch_all_protocol_parameters(&self) -> Result<Vec<Vec<u8>>, DbErr> {
         let res = ProtocolParameters::find()
-            .order_by_desc(protocol_parameters::Column::Slot)
-            .order_by_desc(protocol_parameters::Column::BlockIndex)
-            .one(&self.conn)
+            .order_by_asc(protocol_parameters::Column::Slot)
+            .order_by_asc(protocol_parameters::Column::BlockIndex)
+            .all(&self.conn)
             .await?;
 
-        Ok(res.map(|r| r.update_cbor))
+        Ok(res.into_iter().map(|r| r.update_cbor).collect())
     }
 
     // Rollback
@@ -287,6 +507,38 @@ impl WalletDB {
             let _ = tx_model.delete(&txn).await?;
         }
 
+        let asset_models = HistoryAsset::find()
+            .filter(Condition::all().add(history_asset::Column::Slot.gt(slot)))
+            .all(&txn)
+            .await?;
+
+        for asset_model in asset_models {
+            let _ = asset_model.delete(&txn).await?;
+        }
+
+        // Stake
+
+        let stake_models = Stake::find()
+            .filter(Condition::all().add(stake::Column::Slot.gt(slot)))
+            .all(&txn)
+            .await?;
+
+        for stake_model in stake_models {
+            let _ = stake_model.delete(&txn).await?;
+        }
+
+        // Wallet Addresses
+
+        WalletAddress::update_many()
+            .col_expr(wallet_address::Column::Used, Expr::value(false))
+            .col_expr(
+                wallet_address::Column::UsedSlot,
+                Expr::value(Option::<i64>::None),
+            )
+            .filter(wallet_address::Column::UsedSlot.gt(slot))
+            .exec(&txn)
+            .await?;
+
         // Recent Points
 
         let points_models = RecentPoints::find()
@@ -309,7 +561,7 @@ impl WalletDB {
             let _ = pparams_model.delete(&txn).await?;
         }
 
-        Ok(())
+        txn.commit().await
     }
 
     // Transactions
@@ -366,7 +618,7 @@ mod tests {
     };
     use sea_orm::{Database, Order};
 
-    use super::WalletDB;
+    use super::{entities::stake::Kind, WalletDB};
 
     #[tokio::test]
     async fn insert_utxos() {
@@ -487,4 +739,202 @@ mod tests {
 
         std::fs::remove_file("/tmp/test_remove_utxos.sqlite").unwrap();
     }
+
+    #[tokio::test]
+    async fn history_assets_rollback() {
+        let sqlite_url = format!("sqlite:/tmp/test_history_assets.sqlite?mode=rwc");
+        let db = Database::connect(&sqlite_url).await.unwrap();
+
+        let wallet_db = WalletDB {
+            name: "test_history_assets".into(),
+            path: sqlite_url.into(),
+            conn: db,
+        };
+
+        wallet_db.migrate_up().await.unwrap();
+
+        let tx_hash = [1u8; 32];
+        let policy = vec![2u8; 28];
+
+        let deltas = vec![
+            (policy.clone(), b"token".to_vec(), -5),
+            (policy.clone(), b"other".to_vec(), i128::from(u64::MAX) * 2),
+        ];
+
+        wallet_db
+            .insert_history_tx(tx_hash, 100, 0, 1i128.to_be_bytes().to_vec())
+            .await
+            .unwrap();
+
+        wallet_db
+            .insert_history_assets(tx_hash, 100, 0, deltas)
+            .await
+            .unwrap();
+
+        let stored = wallet_db.fetch_history_assets(&tx_hash).await.unwrap();
+        assert_eq!(stored.len(), 2);
+
+        let for_policy = wallet_db
+            .paginate_tx_history_for_policy(&policy, Order::Asc, None)
+            .fetch()
+            .await
+            .unwrap();
+        assert_eq!(for_policy.len(), 1);
+
+        wallet_db.rollback_to_slot(50).await.unwrap();
+
+        let stored = wallet_db.fetch_history_assets(&tx_hash).await.unwrap();
+        assert!(stored.is_empty());
+
+        drop(wallet_db);
+
+        std::fs::remove_file("/tmp/test_history_assets.sqlite").unwrap();
+    }
+
+    #[tokio::test]
+    async fn stake_events_rollback() {
+        let sqlite_url = format!("sqlite:/tmp/test_stake_events.sqlite?mode=rwc");
+        let db = Database::connect(&sqlite_url).await.unwrap();
+
+        let wallet_db = WalletDB {
+            name: "test_stake_events".into(),
+            path: sqlite_url.into(),
+            conn: db,
+        };
+
+        wallet_db.migrate_up().await.unwrap();
+
+        wallet_db
+            .insert_stake_event([1u8; 32], 100, 0, Kind::Registration, None, None)
+            .await
+            .unwrap();
+
+        wallet_db
+            .insert_stake_event(
+                [1u8; 32],
+                100,
+                0,
+                Kind::Delegation,
+                Some(vec![2u8; 28]),
+                None,
+            )
+            .await
+            .unwrap();
+
+        wallet_db
+            .insert_stake_event([3u8; 32], 200, 1, Kind::Withdrawal, None, Some(5_000_000))
+            .await
+            .unwrap();
+
+        let stored = wallet_db.fetch_stake_events().await.unwrap();
+        assert_eq!(stored.len(), 3);
+        assert_eq!(stored[1].kind, Kind::Delegation);
+        assert_eq!(stored[2].amount, Some(5_000_000));
+
+        wallet_db.rollback_to_slot(150).await.unwrap();
+
+        let stored = wallet_db.fetch_stake_events().await.unwrap();
+        assert_eq!(stored.len(), 2);
+        assert!(stored.iter().all(|x| x.slot <= 150));
+
+        drop(wallet_db);
+
+        std::fs::remove_file("/tmp/test_stake_events.sqlite").unwrap();
+    }
+
+    #[tokio::test]
+    async fn wallet_addresses() {
+        let sqlite_url = format!("sqlite:/tmp/test_wallet_addresses.sqlite?mode=rwc");
+        let db = Database::connect(&sqlite_url).await.unwrap();
+
+        let wallet_db = WalletDB {
+            name: "test_wallet_addresses".into(),
+            path: sqlite_url.into(),
+            conn: db,
+        };
+
+        wallet_db.migrate_up().await.unwrap();
+
+        for index in 0..3 {
+            wallet_db
+                .insert_wallet_address(0, index, [index as u8; 28], false)
+                .await
+                .unwrap();
+        }
+
+        let used = wallet_db
+            .mark_wallet_addresses_used(vec![vec![0u8; 28]], 100)
+            .await
+            .unwrap();
+        assert!(used);
+
+        let used_again = wallet_db
+            .mark_wallet_addresses_used(vec![vec![0u8; 28]], 100)
+            .await
+            .unwrap();
+        assert!(!used_again);
+
+        let issued = wallet_db.issue_wallet_address(0).await.unwrap().unwrap();
+        assert_eq!(issued.address_index, 1);
+
+        let issued = wallet_db.issue_wallet_address(0).await.unwrap().unwrap();
+        assert_eq!(issued.address_index, 2);
+
+        assert!(wallet_db.issue_wallet_address(0).await.unwrap().is_none());
+
+        let unused = wallet_db
+            .fetch_unused_issued_wallet_address(0)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(unused.address_index, 1);
+
+        wallet_db.rollback_to_slot(50).await.unwrap();
+
+        let addresses = wallet_db.fetch_wallet_addresses().await.unwrap();
+        assert!(addresses.iter().all(|x| !x.used && x.used_slot.is_none()));
+
+        drop(wallet_db);
+
+        std::fs::remove_file("/tmp/test_wallet_addresses.sqlite").unwrap();
+    }
+
+    #[tokio::test]
+    async fn protocol_parameters_order() {
+        let sqlite_url = format!("sqlite:/tmp/test_protocol_parameters.sqlite?mode=rwc");
+        let db = Database::connect(&sqlite_url).await.unwrap();
+
+        let wallet_db = WalletDB {
+            name: "test_protocol_parameters".into(),
+            path: sqlite_url.into(),
+            conn: db,
+        };
+
+        wallet_db.migrate_up().await.unwrap();
+
+        wallet_db
+            .insert_protocol_parameters(200, 0, vec![3])
+            .await
+            .unwrap();
+        wallet_db
+            .insert_protocol_parameters(100, 1, vec![2])
+            .await
+            .unwrap();
+        wallet_db
+            .insert_protocol_parameters(100, 0, vec![1])
+            .await
+            .unwrap();
+
+        let updates = wallet_db.fetch_all_protocol_parameters().await.unwrap();
+        assert_eq!(updates, vec![vec![1], vec![2], vec![3]]);
+
+        wallet_db.rollback_to_slot(150).await.unwrap();
+
+        let updates = wallet_db.fetch_all_protocol_parameters().await.unwrap();
+        assert_eq!(updates, vec![vec![1], vec![2]]);
+
+        drop(wallet_db);
+
+        std::fs::remove_file("/tmp/test_protocol_parameters.sqlite").unwrap();
+    }
 }
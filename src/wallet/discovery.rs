@@ -0,0 +1,121 @@
+use miette::{miette, Context, IntoDiagnostic};
+use pallas::wallet::hd::Bip32PublicKey;
+
+use super::{
+    config::Wallet,
+    dal::{entities::wallet_address::Model as WalletAddressModel, WalletDB},
+    hd,
+};
+
+/// Derive new addresses from the account public key until every role has
+/// `GAP_LIMIT` addresses after its last used one. Returns true if any address
+/// was added.
+pub async fn ensure_gap(wallet_db: &WalletDB, account: &Bip32PublicKey) -> miette::Result<bool> {
+    let known = wallet_db.fetch_wallet_addresses().await.into_diagnostic()?;
+
+    let mut added = false;
+
+    for role in [hd::ROLE_EXTERNAL, hd::ROLE_INTERNAL] {
+        let of_role: Vec<&WalletAddressModel> =
+            known.iter().filter(|x| x.role == role as i32).collect();
+
+        let next = of_role
+            .iter()
+            .map(|x| x.address_index as u32 + 1)
+            .max()
+            .unwrap_or(0);
+
+        let first_unused = of_role
+            .iter()
+            .filter(|x| x.used)
+            .map(|x| x.address_index as u32 + 1)
+            .max()
+            .unwrap_or(0);
+
+        for index in next..first_unused + hd::GAP_LIMIT {
+            let hash = hd::derive_key_hash(account, role, index)?;
+
+            // the first external address is the main wallet address, which
+            // is handed out from the start
+            let issued = role == hd::ROLE_EXTERNAL && index == 0;
+
+            wallet_db
+                .insert_wallet_address(role, index, *hash, issued)
+                .await
+                .into_diagnostic()?;
+
+            added = true;
+        }
+    }
+
+    Ok(added)
+}
+
+/// Credentials whose outputs and certificates are tracked by the wallet
+#[derive(Debug, Clone, Default)]
+pub struct Watched {
+    /// payment key hashes: the main key and, for HD wallets, all the derived
+    /// addresses
+    pub pkhs: Vec<[u8; 28]>,
+    /// hashes of the scripts registered in the wallet
+    pub scripts: Vec<[u8; 28]>,
+    pub stake: Option<[u8; 28]>,
+}
+
+fn parse_hash(value: &str) -> miette::Result<[u8; 28]> {
+    hex::decode(value)
+        .into_diagnostic()
+        .context("parsing hash hex")?
+        .try_into()
+        .map_err(|_| miette!("hash incorrect length"))
+}
+
+pub async fn watched(wallet: &Wallet, wallet_db: &WalletDB) -> miette::Result<Watched> {
+    let mut pkhs = vec![];
+
+    // the main credential of multisig wallets is a script hash, already part
+    // of the registered scripts
+    if wallet.keys.multisig_script.is_none() {
+        pkhs.push(parse_hash(&wallet.keys.public_key_hash)?);
+    }
+
+    for address in wallet_db.fetch_wallet_addresses().await.into_diagnostic()? {
+        let hash: [u8; 28] = address
+            .payment_cred
+            .try_into()
+            .map_err(|_| miette!("malformed payment credential in wallet db"))?;
+
+        if !pkhs.contains(&hash) {
+            pkhs.push(hash);
+        }
+    }
+
+    let scripts = wallet
+        .scripts
+        .iter()
+        .map(|x| parse_hash(x))
+        .collect::<miette::Result<_>>()?;
+
+    let stake = wallet
+        .keys
+        .stake_key_hash
+        .as_deref()
+        .map(parse_hash)
+        .transpose()?;
+
+    Ok(Watched {
+        pkhs,
+        scripts,
+        stake,
+    })
+}
+
+/// Account public key of an HD wallet, if the wallet has one
+pub fn account_public_key(wallet: &Wallet) -> miette::Result<Option<Bip32PublicKey>> {
+    wallet
+        .keys
+        .account_public
+        .as_deref()
+        .map(hd::parse_account_public_key)
+        .transpose()
+}
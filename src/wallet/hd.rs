@@ -0,0 +1,179 @@
+use bech32::FromBase32;
+use bip39::Mnemonic;
+use miette::{bail, miette, IntoDiagnostic};
+use pallas::{
+    crypto::{hash::Hash, key::ed25519::SecretKey},
+    ledger::traverse::ComputeHash,
+    wallet::{
+        hd::{Bip32PrivateKey, Bip32PublicKey},
+        wrapper, PrivateKey,
+    },
+};
+use rand::{rngs::OsRng, RngCore};
+
+const HARDENED: u32 = 0x80000000;
+
+// CIP-1852 derivation path m / purpose' / coin_type' / account' / role / index
+const PURPOSE: u32 = 1852;
+const COIN_TYPE: u32 = 1815;
+
+pub const ROLE_EXTERNAL: u32 = 0;
+pub const ROLE_INTERNAL: u32 = 1;
+pub const ROLE_STAKE: u32 = 2;
+
+/// Amount of consecutive unused addresses kept after the last used one for
+/// each role (BIP44)
+pub const GAP_LIMIT: u32 = 20;
+
+/// Generate a new BIP39 english mnemonic with the given amount of words
+pub fn generate_mnemonic(words: usize) -> miette::Result<Mnemonic> {
+    let entropy_size = match words {
+        12 => 16,
+        15 => 20,
+        24 => 32,
+        _ => bail!("mnemonic must have 12, 15 or 24 words"),
+    };
+
+    let mut entropy = vec![0u8; entropy_size];
+    OsRng.fill_bytes(&mut entropy);
+
+    Mnemonic::from_entropy(&entropy).into_diagnostic()
+}
+
+/// Icarus-style root key for a mnemonic, as used by other Cardano wallets
+pub fn root_from_mnemonic(mnemonic: &Mnemonic) -> miette::Result<Bip32PrivateKey> {
+    Bip32PrivateKey::from_bip39_mnenomic(mnemonic.to_string(), String::new())
+        .map_err(|e| miette!("invalid mnemonic: {e}"))
+}
+
+pub fn derive_account(root: &Bip32PrivateKey, account: u32) -> Bip32PrivateKey {
+    root.derive(HARDENED | PURPOSE)
+        .derive(HARDENED | COIN_TYPE)
+        .derive(HARDENED | account)
+}
+
+pub fn derive_key(account_key: &Bip32PrivateKey, role: u32, index: u32) -> Bip32PrivateKey {
+    account_key.derive(role).derive(index)
+}
+
+/// Extended public key of the first account, which allows deriving the
+/// payment key hashes of the wallet without the spending password
+pub fn account_public_key(root: &Bip32PrivateKey) -> Bip32PublicKey {
+    derive_account(root, 0).to_public()
+}
+
+/// Parse an account public key given as hex or bech32 (acct_xvk)
+pub fn parse_account_public_key(value: &str) -> miette::Result<Bip32PublicKey> {
+    let bytes = match bech32::decode(value) {
+        Ok((_, data, _)) => Vec::<u8>::from_base32(&data).into_diagnostic()?,
+        Err(_) => hex::decode(value).into_diagnostic()?,
+    };
+
+    let bytes: [u8; 64] = bytes
+        .try_into()
+        .map_err(|_| miette!("account public key incorrect length"))?;
+
+    Ok(Bip32PublicKey::from_bytes(bytes))
+}
+
+pub fn derive_key_hash(
+    account_public: &Bip32PublicKey,
+    role: u32,
+    index: u32,
+) -> miette::Result<Hash<28>> {
+    let key = account_public
+        .derive(role)
+        .and_then(|x| x.derive(index))
+        .map_err(|e| miette!("could not derive public key: {e:?}"))?;
+
+    Ok(key.to_ed25519_pubkey().compute_hash())
+}
+
+/// Encrypt an HD root key using the same scheme as the wallet private keys.
+/// The extended secret key and the chain code are wrapped separately and
+/// concatenated.
+pub fn encrypt_root_key(root: &Bip32PrivateKey, password: &String) -> Vec<u8> {
+    let chain_code: PrivateKey = SecretKey::from(root.chain_code()).into();
+
+    let mut out = wrapper::encrypt_private_key(OsRng, root.to_ed25519_private_key(), password);
+    out.extend(wrapper::encrypt_private_key(OsRng, chain_code, password));
+
+    out
+}
+
+pub fn decrypt_root_key(password: &String, data: Vec<u8>) -> miette::Result<Bip32PrivateKey> {
+    // both wrapped parts carry the same overhead (salt, nonce and tag)
+    let overhead = data.len().saturating_sub(96) / 2;
+
+    if data.len() != 96 + 2 * overhead {
+        bail!("malformed encrypted root key");
+    }
+    let (key_part, chain_code_part) = data.split_at(overhead + 64);
+
+    let decrypt = |part: &[u8]| {
+        wrapper::decrypt_private_key(password, part.to_vec())
+            .map_err(|_| miette!("could not decrypt root key"))
+    };
+
+    let mut bytes = [0u8; 96];
+
+    match decrypt(key_part)? {
+        x @ PrivateKey::Extended(_) => bytes[..64].copy_from_slice(&x.as_bytes()),
+        _ => bail!("malformed encrypted root key"),
+    }
+
+    match decrypt(chain_code_part)? {
+        x @ PrivateKey::Normal(_) => bytes[64..].copy_from_slice(&x.as_bytes()),
+        _ => bail!("malformed encrypted root key"),
+    }
+
+    Bip32PrivateKey::from_bytes(bytes).map_err(|e| miette!("invalid root key: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mnemonic_word_count() {
+        for words in [12, 15, 24] {
+            let mnemonic = generate_mnemonic(words).unwrap();
+            assert_eq!(mnemonic.word_count(), words);
+        }
+
+        assert!(generate_mnemonic(13).is_err());
+    }
+
+    #[test]
+    fn root_key_roundtrip() {
+        let password = String::from("secret");
+
+        let mnemonic = generate_mnemonic(24).unwrap();
+        let root = root_from_mnemonic(&mnemonic).unwrap();
+
+        let encrypted = encrypt_root_key(&root, &password);
+        let decrypted = decrypt_root_key(&password, encrypted.clone()).unwrap();
+
+        assert_eq!(root.as_bytes(), decrypted.as_bytes());
+
+        assert!(decrypt_root_key(&String::from("wrong"), encrypted).is_err());
+    }
+
+    #[test]
+    fn public_derivation_matches_private() {
+        let mnemonic = generate_mnemonic(24).unwrap();
+        let root = root_from_mnemonic(&mnemonic).unwrap();
+
+        let account = derive_account(&root, 0);
+        let account_public = account_public_key(&root);
+
+        for index in [0, 1, 7] {
+            let private = derive_key(&account, ROLE_INTERNAL, index);
+
+            assert_eq!(
+                derive_key_hash(&account_public, ROLE_INTERNAL, index).unwrap(),
+                private.to_ed25519_private_key().public_key().compute_hash()
+            );
+        }
+    }
+}
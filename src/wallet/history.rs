@@ -1,25 +1,108 @@
-use clap::Parser;
-use comfy_table::Table;
+use clap::{Parser, ValueEnum};
+use miette::{miette, Context, IntoDiagnostic};
+use sea_orm::Order;
 use tracing::instrument;
 
+use crate::{
+    chain::config::Chain,
+    utils::OutputFormatter,
+    wallet::{
+        config::{HistoryView, Wallet},
+        dal::WalletDB,
+    },
+    OutputFormat,
+};
+
 #[derive(Parser)]
 pub struct Args {
     /// name of the wallet to query
     #[arg(env = "CARDAMINAL_DEFAULT_WALLET")]
     wallet: Option<String>,
+
+    /// Number of page to find
+    #[arg(short, long, default_value_t = 0)]
+    page: u64,
+
+    /// Number of transactions per page
+    #[arg(short, long, default_value_t = 20)]
+    size: u64,
+
+    /// Order of the transactions by slot
+    #[arg(long, default_value = "desc")]
+    order: SortOrder,
+
+    /// Only show transactions which moved assets of this policy id (hex)
+    #[arg(long)]
+    policy: Option<String>,
+}
+
+#[derive(ValueEnum, Clone)]
+enum SortOrder {
+    Asc,
+    Desc,
+}
+
+impl From<SortOrder> for Order {
+    fn from(value: SortOrder) -> Self {
+        match value {
+            SortOrder::Asc => Order::Asc,
+            SortOrder::Desc => Order::Desc,
+        }
+    }
 }
 
 #[instrument("history", skip_all)]
-pub async fn run(_args: Args) -> miette::Result<()> {
-    let mut table = Table::new();
+pub async fn run(args: Args, ctx: &crate::Context) -> miette::Result<()> {
+    let wallet_name = args.wallet.ok_or(miette!("wallet param is required"))?;
+
+    let wallet = Wallet::load_config(&ctx.dirs.root_dir, &wallet_name)?
+        .ok_or(miette!("wallet doesn't exist"))?;
+
+    let chain = match &wallet.chain {
+        Some(name) => Chain::load_config(&ctx.dirs.root_dir, name)?,
+        None => None,
+    };
+
+    let wallet_db = WalletDB::open(&wallet.name, &Wallet::dir(&ctx.dirs.root_dir, &wallet.name))
+        .await
+        .into_diagnostic()?;
+
+    let paginator = match &args.policy {
+        Some(policy) => {
+            let policy: [u8; 28] = hex::decode(policy)
+                .into_diagnostic()
+                .context("parsing policy hex")?
+                .try_into()
+                .map_err(|_| miette!("policy id incorrect length"))?;
+
+            wallet_db.paginate_tx_history_for_policy(&policy, args.order.into(), Some(args.size))
+        }
+        None => wallet_db.paginate_tx_history(args.order.into(), Some(args.size)),
+    };
+
+    let history = paginator.fetch_page(args.page).await.into_diagnostic()?;
+
+    let mut history_view = vec![];
+
+    for entry in history {
+        let assets = wallet_db
+            .fetch_history_assets(&entry.tx_hash)
+            .await
+            .into_diagnostic()?;
+
+        history_view.push(HistoryView::try_from(entry)?.with_assets(assets)?);
+    }
 
-    table
-        .set_header(vec!["slot", "tx", "idx", "change"])
-        .add_row(vec!["123456", "abcdabcdabcd", "0", "+24 ADA"])
-        .add_row(vec!["123589", "abcdabcdabcd", "1", "-4 ADA"])
-        .add_row(vec!["123763", "abcdabcdabcd", "0", "+200 ADA"]);
+    if let Some(chain) = &chain {
+        for entry in history_view.iter_mut() {
+            entry.block_time = chain.slot_to_time(entry.slot);
+        }
+    }
 
-    println!("{table}");
+    match ctx.output_format {
+        OutputFormat::Json => history_view.to_json(),
+        OutputFormat::Table => history_view.to_table(),
+    }
 
     Ok(())
 }
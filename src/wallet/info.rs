@@ -1,10 +1,10 @@
 use clap::Parser;
-use miette::bail;
+use miette::{bail, IntoDiagnostic};
 use tracing::instrument;
 
 use crate::{utils::OutputFormatter, OutputFormat};
 
-use super::config;
+use super::{config, dal::WalletDB};
 
 #[derive(Parser)]
 pub struct Args {
@@ -24,9 +24,20 @@ pub async fn run(args: Args, ctx: &crate::Context) -> miette::Result<()> {
     println!("local storage dir: {}", &dir.to_string_lossy());
 
     if let Some(cfg) = cfg {
+        let mut info = config::WalletInfoView::new(cfg);
+
+        if info.wallet.keys.stake_key_hash.is_some() {
+            let wallet_db = WalletDB::open(&info.wallet.name, &dir)
+                .await
+                .into_diagnostic()?;
+            let events = wallet_db.fetch_stake_events().await.into_diagnostic()?;
+
+            info = info.with_stake(config::StakeView::from_events(&events));
+        }
+
         match ctx.output_format {
-            OutputFormat::Table => cfg.to_table(),
-            OutputFormat::Json => cfg.to_json(),
+            OutputFormat::Table => info.to_table(),
+            OutputFormat::Json => info.to_json(),
         }
     }
 
@@ -8,9 +8,14 @@ pub mod config;
 mod create;
 pub mod dal;
 mod detach;
+pub mod discovery;
+pub mod hd;
 mod history;
 mod info;
 mod list;
+pub mod multisig;
+mod restore;
+mod script;
 mod select;
 mod update;
 mod utxos;
@@ -25,6 +30,8 @@ pub struct Args {
 enum Commands {
     /// create a new wallet
     Create(create::Args),
+    /// restore a wallet from a mnemonic
+    Restore(restore::Args),
     /// show wallet info
     Info(info::Args),
     /// show wallet address
@@ -45,12 +52,15 @@ enum Commands {
     Select(select::Args),
     /// show wallet balance
     Balance(balance::Args),
+    /// manage the scripts whose outputs are tracked by the wallet
+    Script(script::Args),
 }
 
 #[instrument("wallet", skip_all)]
 pub async fn run(args: Args, ctx: &crate::Context) -> miette::Result<()> {
     match args.command {
         Commands::Create(args) => create::run(args, ctx).await,
+        Commands::Restore(args) => restore::run(args, ctx).await,
         Commands::Address(args) => address::run(args, ctx).await,
         Commands::Info(args) => info::run(args, ctx).await,
         Commands::List(args) => list::run(args, ctx).await,
@@ -63,9 +73,10 @@ pub async fn run(args: Args, ctx: &crate::Context) -> miette::Result<()> {
             attach::run(args, ctx).await
         }
         Commands::Detach(args) => detach::run(args, ctx).await,
-        Commands::History(args) => history::run(args).await,
+        Commands::History(args) => history::run(args, ctx).await,
         Commands::Utxos(args) => utxos::run(args, ctx).await,
         Commands::Select(args) => select::run(args, ctx).await,
         Commands::Balance(args) => balance::run(args, ctx).await,
+        Commands::Script(args) => script::run(args, ctx).await,
     }
 }
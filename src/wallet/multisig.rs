@@ -0,0 +1,275 @@
+use miette::{bail, miette, Context, IntoDiagnostic};
+use pallas::{
+    codec::minicbor,
+    crypto::hash::Hash,
+    ledger::{
+        primitives::{alonzo::NativeScript, babbage},
+        traverse::ComputeHash,
+    },
+};
+use serde_json::Value as JsonValue;
+
+/// Parse a native script in the JSON format used by cardano-cli, made of
+/// `sig`, `all`, `any`, `atLeast`, `after` and `before` nodes
+pub fn from_json(value: &JsonValue) -> miette::Result<NativeScript> {
+    let kind = value["type"]
+        .as_str()
+        .ok_or(miette!("native script node without type"))?;
+
+    let scripts = || -> miette::Result<Vec<NativeScript>> {
+        value["scripts"]
+            .as_array()
+            .ok_or(miette!("'{kind}' script without scripts"))?
+            .iter()
+            .map(from_json)
+            .collect()
+    };
+
+    let slot = || {
+        value["slot"]
+            .as_u64()
+            .ok_or(miette!("'{kind}' script without slot"))
+    };
+
+    let script = match kind {
+        "sig" => {
+            let hash: Hash<28> = value["keyHash"]
+                .as_str()
+                .ok_or(miette!("'sig' script without keyHash"))?
+                .parse()
+                .map_err(|_| miette!("invalid key hash in 'sig' script"))?;
+
+            NativeScript::ScriptPubkey(hash)
+        }
+        "all" => NativeScript::ScriptAll(scripts()?),
+        "any" => NativeScript::ScriptAny(scripts()?),
+        "atLeast" => {
+            let required = value["required"]
+                .as_u64()
+                .ok_or(miette!("'atLeast' script without required"))?;
+
+            let scripts = scripts()?;
+
+            if required as usize > scripts.len() {
+                bail!("'atLeast' script requires more signatures than it has scripts");
+            }
+
+            NativeScript::ScriptNOfK(required as u32, scripts)
+        }
+        "after" => NativeScript::InvalidBefore(slot()?),
+        "before" => NativeScript::InvalidHereafter(slot()?),
+        x => bail!("unknown native script type '{x}'"),
+    };
+
+    Ok(script)
+}
+
+pub fn to_cbor(script: &NativeScript) -> miette::Result<Vec<u8>> {
+    minicbor::to_vec(script).into_diagnostic()
+}
+
+pub fn from_cbor(bytes: &[u8]) -> miette::Result<NativeScript> {
+    minicbor::decode(bytes)
+        .into_diagnostic()
+        .context("decoding native script")
+}
+
+/// Hash of the script, which is also the payment credential of the addresses
+/// it locks
+pub fn hash(script: &NativeScript) -> Hash<28> {
+    script.compute_hash()
+}
+
+/// All the key hashes appearing in the script
+pub fn key_hashes(script: &NativeScript) -> Vec<Hash<28>> {
+    let mut out = vec![];
+
+    match script {
+        NativeScript::ScriptPubkey(hash) => out.push(*hash),
+        NativeScript::ScriptAll(xs)
+        | NativeScript::ScriptAny(xs)
+        | NativeScript::ScriptNOfK(_, xs) => {
+            for x in xs {
+                out.extend(key_hashes(x));
+            }
+        }
+        NativeScript::InvalidBefore(_) | NativeScript::InvalidHereafter(_) => (),
+    }
+
+    out.sort();
+    out.dedup();
+
+    out
+}
+
+/// Validity interval of a transaction, which the time locks of native
+/// scripts are checked against
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Validity {
+    pub valid_from_slot: Option<u64>,
+    pub invalid_from_slot: Option<u64>,
+}
+
+impl Validity {
+    pub fn of(body: &babbage::TransactionBody) -> Self {
+        Self {
+            valid_from_slot: body.validity_interval_start,
+            invalid_from_slot: body.ttl,
+        }
+    }
+}
+
+/// True if signatures from the given key hashes are enough to satisfy the
+/// script within the validity interval of the transaction. A time lock only
+/// holds if the interval is bounded on its side: `after` needs a validity
+/// start at or past its slot, `before` a validity end at or before it.
+pub fn is_satisfied(script: &NativeScript, signed: &[Hash<28>], validity: &Validity) -> bool {
+    match script {
+        NativeScript::ScriptPubkey(hash) => signed.contains(hash),
+        NativeScript::ScriptAll(xs) => xs.iter().all(|x| is_satisfied(x, signed, validity)),
+        NativeScript::ScriptAny(xs) => xs.iter().any(|x| is_satisfied(x, signed, validity)),
+        NativeScript::ScriptNOfK(n, xs) => {
+            xs.iter()
+                .filter(|x| is_satisfied(x, signed, validity))
+                .count()
+                >= *n as usize
+        }
+        NativeScript::InvalidBefore(slot) => validity.valid_from_slot.is_some_and(|x| x >= *slot),
+        NativeScript::InvalidHereafter(slot) => {
+            validity.invalid_from_slot.is_some_and(|x| x <= *slot)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEYS: [&str; 3] = [
+        "0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a",
+        "0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b",
+        "0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c",
+    ];
+
+    #[test]
+    fn two_of_three() {
+        let sigs: Vec<_> = KEYS
+            .iter()
+            .map(|x| serde_json::json!({"type": "sig", "keyHash": x}))
+            .collect();
+
+        let json = serde_json::json!({"type": "atLeast", "required": 2, "scripts": sigs});
+
+        let script = from_json(&json).unwrap();
+        let keys: Vec<Hash<28>> = KEYS.iter().map(|x| x.parse().unwrap()).collect();
+
+        assert_eq!(key_hashes(&script), keys);
+        let validity = Validity::default();
+
+        assert!(!is_satisfied(&script, &keys[..1], &validity));
+        assert!(is_satisfied(&script, &keys[1..], &validity));
+        assert!(is_satisfied(&script, &[keys[0], keys[2]], &validity));
+        assert!(is_satisfied(&script, &keys, &validity));
+        assert!(!is_satisfied(&script, &[], &validity));
+
+        let decoded = from_cbor(&to_cbor(&script).unwrap()).unwrap();
+        assert_eq!(hash(&decoded), hash(&script));
+    }
+
+    #[test]
+    fn time_locks() {
+        let json = serde_json::json!({
+            "type": "all",
+            "scripts": [
+                {"type": "sig", "keyHash": KEYS[0]},
+                {"type": "after", "slot": 100},
+                {"type": "before", "slot": 200},
+            ],
+        });
+
+        let script = from_json(&json).unwrap();
+        let keys: Vec<Hash<28>> = vec![KEYS[0].parse().unwrap()];
+
+        let validity = |from, until| Validity {
+            valid_from_slot: from,
+            invalid_from_slot: until,
+        };
+
+        assert!(is_satisfied(
+            &script,
+            &keys,
+            &validity(Some(100), Some(200))
+        ));
+        assert!(is_satisfied(
+            &script,
+            &keys,
+            &validity(Some(150), Some(160))
+        ));
+        assert!(!is_satisfied(&script, &[], &validity(Some(100), Some(200))));
+
+        // the interval has to be bounded on the side of each lock
+        assert!(!is_satisfied(&script, &keys, &validity(None, Some(200))));
+        assert!(!is_satisfied(&script, &keys, &validity(Some(100), None)));
+
+        // and fall within the locks
+        assert!(!is_satisfied(
+            &script,
+            &keys,
+            &validity(Some(99), Some(200))
+        ));
+        assert!(!is_satisfied(
+            &script,
+            &keys,
+            &validity(Some(100), Some(201))
+        ));
+    }
+
+    #[test]
+    fn time_lock_alternatives() {
+        // one of two keys, or the first key alone after slot 500
+        let json = serde_json::json!({
+            "type": "atLeast",
+            "required": 1,
+            "scripts": [
+                {"type": "all", "scripts": [
+                    {"type": "sig", "keyHash": KEYS[0]},
+                    {"type": "sig", "keyHash": KEYS[1]},
+                ]},
+                {"type": "all", "scripts": [
+                    {"type": "sig", "keyHash": KEYS[0]},
+                    {"type": "after", "slot": 500},
+                ]},
+            ],
+        });
+
+        let script = from_json(&json).unwrap();
+        let keys: Vec<Hash<28>> = KEYS.iter().map(|x| x.parse().unwrap()).collect();
+
+        let before = Validity {
+            valid_from_slot: Some(400),
+            invalid_from_slot: None,
+        };
+
+        let after = Validity {
+            valid_from_slot: Some(500),
+            invalid_from_slot: None,
+        };
+
+        assert!(is_satisfied(&script, &keys[..2], &before));
+        assert!(!is_satisfied(&script, &keys[..1], &before));
+        assert!(is_satisfied(&script, &keys[..1], &after));
+        assert!(!is_satisfied(&script, &keys[1..], &after));
+    }
+
+    #[test]
+    fn invalid_scripts() {
+        assert!(from_json(&serde_json::json!({"type": "sig", "keyHash": "00"})).is_err());
+        assert!(from_json(&serde_json::json!({"type": "after"})).is_err());
+        assert!(from_json(&serde_json::json!({
+            "type": "atLeast",
+            "required": 2,
+            "scripts": [{"type": "before", "slot": 10}],
+        }))
+        .is_err());
+    }
+}
@@ -0,0 +1,76 @@
+use bip39::Mnemonic;
+use clap::Parser;
+use miette::{bail, IntoDiagnostic};
+use tracing::instrument;
+
+use super::{create, hd};
+
+pub fn gather_inputs(args: &mut Args) -> miette::Result<()> {
+    if args.mnemonic.is_none() {
+        let mnemonic = inquire::Password::new("mnemonic:")
+            .with_help_message("the words of the mnemonic separated by spaces")
+            .with_display_mode(inquire::PasswordDisplayMode::Masked)
+            .without_confirmation()
+            .prompt()
+            .into_diagnostic()?;
+
+        args.mnemonic = Some(mnemonic);
+    }
+
+    let password = inquire::Password::new("password:")
+        .with_help_message("the spending password of your wallet")
+        .with_display_mode(inquire::PasswordDisplayMode::Masked)
+        .prompt()
+        .into_diagnostic()?;
+
+    args.password = Some(password);
+
+    Ok(())
+}
+
+#[derive(Parser, Clone)]
+pub struct Args {
+    /// name to identify the wallet
+    pub name: String,
+
+    /// BIP39 mnemonic words of the wallet to restore
+    #[arg(short, long)]
+    mnemonic: Option<String>,
+
+    /// name of the chain to attach the wallet
+    #[arg(short, long, env = "CARDAMINAL_DEFAULT_CHAIN")]
+    pub chain: Option<String>,
+
+    /// spending password used to encrypt the private keys
+    #[arg(short, long)]
+    password: Option<String>,
+
+    /// use interactive mode
+    #[arg(long, short, action)]
+    interactive: bool,
+}
+
+#[instrument("restore", skip_all)]
+pub async fn run(args: Args, ctx: &crate::Context) -> miette::Result<()> {
+    let mut args = args;
+
+    create::check_new_wallet(ctx, &args.name, args.chain.as_deref())?;
+
+    if args.interactive {
+        gather_inputs(&mut args)?;
+    }
+
+    let mnemonic = match &args.mnemonic {
+        Some(m) => Mnemonic::parse(m).into_diagnostic()?,
+        None => bail!("mnemonic is required"),
+    };
+
+    let password = match &args.password {
+        Some(p) => p,
+        None => bail!("password is required"),
+    };
+
+    let key_data = create::hd_keys(&hd::root_from_mnemonic(&mnemonic)?, password);
+
+    create::init_wallet(ctx, args.name, args.chain, key_data).await
+}
@@ -0,0 +1,99 @@
+use clap::{Parser, Subcommand};
+use miette::{bail, miette, IntoDiagnostic};
+use pallas::ledger::addresses::{Address, ShelleyPaymentPart};
+use tracing::{info, instrument, warn};
+
+use super::config::Wallet;
+
+#[derive(Parser)]
+pub struct Args {
+    /// name of the wallet
+    #[arg(env = "CARDAMINAL_DEFAULT_WALLET")]
+    wallet: String,
+
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// start tracking the outputs locked by a script
+    Add {
+        /// hex script hash or bech32 script address
+        script: String,
+    },
+    /// stop tracking the outputs locked by a script
+    Remove {
+        /// hex script hash or bech32 script address
+        script: String,
+    },
+    /// list the scripts tracked by the wallet
+    List,
+}
+
+#[instrument("script", skip_all, fields(wallet=args.wallet))]
+pub async fn run(args: Args, ctx: &crate::Context) -> miette::Result<()> {
+    let mut wallet = Wallet::load_config(&ctx.dirs.root_dir, &args.wallet)?
+        .ok_or(miette!("wallet doesn't exist"))?;
+
+    match args.command {
+        Commands::Add { script } => {
+            let hash = parse_script_hash(&script)?;
+
+            if wallet.scripts.contains(&hash) {
+                bail!("script already tracked by the wallet");
+            }
+
+            wallet.scripts.push(hash);
+            wallet.save_config(&ctx.dirs.root_dir)?;
+
+            info!("script added");
+            warn!("only outputs in blocks processed by future wallet updates will be tracked");
+        }
+        Commands::Remove { script } => {
+            let hash = parse_script_hash(&script)?;
+
+            if !wallet.scripts.contains(&hash) {
+                bail!("script not tracked by the wallet");
+            }
+
+            if wallet.keys.multisig_script.is_some() && wallet.keys.public_key_hash == hash {
+                bail!("can't stop tracking the script of a multisig wallet");
+            }
+
+            wallet.scripts.retain(|x| *x != hash);
+            wallet.save_config(&ctx.dirs.root_dir)?;
+
+            info!("script removed");
+        }
+        Commands::List => {
+            for script in wallet.scripts.iter() {
+                println!("{script}");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Hex script hash from either the hash itself or an address locked by the
+/// script
+fn parse_script_hash(value: &str) -> miette::Result<String> {
+    if let Ok(address) = Address::from_bech32(value) {
+        return match address {
+            Address::Shelley(x) => match x.payment() {
+                ShelleyPaymentPart::Script(h) => Ok(hex::encode(h)),
+                ShelleyPaymentPart::Key(_) => bail!("address is not locked by a script"),
+            },
+            _ => bail!("address is not locked by a script"),
+        };
+    }
+
+    let bytes = hex::decode(value).into_diagnostic()?;
+
+    if bytes.len() != 28 {
+        bail!("script hash incorrect length");
+    }
+
+    Ok(hex::encode(bytes))
+}
@@ -2,13 +2,14 @@ use std::{collections::HashMap, iter};
 
 use clap::Parser;
 use indicatif::ProgressStyle;
-use miette::{bail, miette, Context, IntoDiagnostic};
+use miette::{bail, miette, IntoDiagnostic};
 use pallas::{
     codec::minicbor,
     crypto::hash::Hash,
     ledger::{
-        addresses::{Address, ShelleyPaymentPart},
-        traverse::{Era, MultiEraBlock, MultiEraOutput, MultiEraUpdate},
+        addresses::{Address, ShelleyPaymentPart, StakePayload},
+        primitives::alonzo::{Certificate, StakeCredential},
+        traverse::{Era, MultiEraBlock, MultiEraOutput, MultiEraTx, MultiEraUpdate},
     },
 };
 use tracing::{info, info_span, instrument, Span};
@@ -16,7 +17,11 @@ use tracing_indicatif::span_ext::IndicatifSpanExt;
 
 use crate::{
     chain::config::Chain,
-    wallet::{config::Wallet, dal::WalletDB},
+    wallet::{
+        config::Wallet,
+        dal::{entities::stake::Kind as StakeKind, WalletDB},
+        discovery::{self, Watched},
+    },
 };
 
 #[derive(Parser)]
@@ -57,16 +62,21 @@ pub async fn run(args: Args, ctx: &crate::Context) -> miette::Result<()> {
     let wallet = Wallet::load_config(&ctx.dirs.root_dir, &args.wallet)?
         .ok_or(miette!("wallet not found"))?;
 
-    let wallet_pkh: [u8; 28] = hex::decode(wallet.keys.public_key_hash)
-        .into_diagnostic()
-        .context("parsing public key hex")?
-        .try_into()
-        .map_err(|_| miette!("invalid public key"))?;
-
     let wallet_db = WalletDB::open(&args.wallet, &wallet_path)
         .await
         .into_diagnostic()?;
 
+    // HD wallets watch a gap-limited set of derived addresses, which grows
+    // as addresses get used during the crawl
+
+    let account = discovery::account_public_key(&wallet)?;
+
+    if let Some(account) = &account {
+        discovery::ensure_gap(&wallet_db, account).await?;
+    }
+
+    let mut watched = discovery::watched(&wallet, &wallet_db).await?;
+
     // intersect wallet db with chain
 
     let mut recent_points = wallet_db.paginate_recent_points(None);
@@ -138,9 +148,13 @@ pub async fn run(args: Args, ctx: &crate::Context) -> miette::Result<()> {
 
             let block = MultiEraBlock::decode(&block_bytes).into_diagnostic()?;
 
-            let wallet_pkhs = vec![wallet_pkh];
+            process_block(&wallet_db, &block, &watched).await?;
 
-            process_block(&wallet_db, &block, wallet_pkhs).await?;
+            if let Some(account) = &account {
+                if discovery::ensure_gap(&wallet_db, account).await? {
+                    watched = discovery::watched(&wallet, &wallet_db).await?;
+                }
+            }
 
             info!(last_slot = point.0, "new blocks crawled");
             Span::current().pb_set_position(point.0);
@@ -155,13 +169,13 @@ pub async fn run(args: Args, ctx: &crate::Context) -> miette::Result<()> {
     Ok(())
 }
 
-/// Given a block and a list of public key hashes controlled by the wallet,
-/// modify the different Wallet DB tables according to the contents of the
-/// block in relation to the wallet.
+/// Given a block and the credentials watched by the wallet, modify the
+/// different Wallet DB tables according to the contents of the block in
+/// relation to the wallet.
 pub async fn process_block(
     wallet_db: &WalletDB,
     block: &MultiEraBlock<'_>,
-    wallet_pkhs: Vec<[u8; 28]>,
+    watched: &Watched,
 ) -> miette::Result<()> {
     let txs = block.txs().clone();
 
@@ -171,16 +185,27 @@ pub async fn process_block(
         .iter()
         .flat_map(|tx| iter::repeat(*tx.hash()).zip(tx.produces()))
         .map(|(txid, (idx, txo))| (txid, idx, txo, block.slot(), block.era()))
-        .filter(|(_, _, txo, _, _)| output_controlled_by_pkh(txo, &wallet_pkhs))
+        .filter(|(_, _, txo, _, _)| output_controlled_by_wallet(txo, watched))
         .collect::<Vec<_>>();
 
     let consumed = txs.iter().flat_map(|tx| tx.consumes()).collect::<Vec<_>>();
 
+    let used_pkhs = produced_for_wallet
+        .iter()
+        .filter_map(|(_, _, txo, _, _)| output_pkh(txo))
+        .map(|x| x.to_vec())
+        .collect::<Vec<_>>();
+
     wallet_db
         .insert_utxos(produced_for_wallet)
         .await
         .into_diagnostic()?;
 
+    wallet_db
+        .mark_wallet_addresses_used(used_pkhs, block.slot())
+        .await
+        .into_diagnostic()?;
+
     let removed = wallet_db.remove_utxos(consumed).await.into_diagnostic()?;
 
     // Transaction History
@@ -223,7 +248,7 @@ pub async fn process_block(
 
         // process outputs
         for (_, output) in tx.produces() {
-            if output_controlled_by_pkh(&output, &wallet_pkhs) {
+            if output_controlled_by_wallet(&output, watched) {
                 involved = true;
 
                 *value_deltas
@@ -260,11 +285,9 @@ pub async fn process_block(
                 .remove(&vec![])
                 .unwrap();
 
-            // TODO: value delta stored in WalletDB is currently just lovelace
-            // I was going to include value delta as CBOR encoding of a Value
-            // object, but Value can only hold u64, whereas we need negative
-            // amounts and amounts larger than 64 bit (as we potentially sum
-            // u64 values), for now it is just lovelace change i128 big endian
+            // lovelace delta is stored as i128 big endian in the history
+            // table, while the non-ada deltas go to their own table as a
+            // Value can't hold negative or larger than u64 amounts
             wallet_db
                 .insert_history_tx(
                     *tx.hash(),
@@ -274,6 +297,31 @@ pub async fn process_block(
                 )
                 .await
                 .into_diagnostic()?;
+
+            let asset_deltas = value_deltas
+                .into_iter()
+                .flat_map(|(policy, assets)| {
+                    assets
+                        .into_iter()
+                        .map(move |(name, delta)| (policy.clone(), name, delta))
+                })
+                .filter(|(_, _, delta)| *delta != 0)
+                .collect::<Vec<_>>();
+
+            if !asset_deltas.is_empty() {
+                wallet_db
+                    .insert_history_assets(*tx.hash(), block.slot(), blk_idx as u16, asset_deltas)
+                    .await
+                    .into_diagnostic()?;
+            }
+        }
+    }
+
+    // Stake
+
+    if let Some(stake_hash) = watched.stake {
+        for (blk_idx, tx) in txs.iter().enumerate().filter(|(_, x)| x.is_valid()) {
+            process_stake_events(wallet_db, block.slot(), blk_idx as u16, tx, &stake_hash).await?;
         }
     }
 
@@ -304,14 +352,86 @@ pub async fn process_block(
     Ok(())
 }
 
-fn output_controlled_by_pkh(txo: &MultiEraOutput<'_>, pkhs: &[[u8; 28]]) -> bool {
-    let controlling_pkh = match txo.address().unwrap() {
+/// Record the certificates and withdrawals of a transaction which involve
+/// the wallet stake key
+async fn process_stake_events(
+    wallet_db: &WalletDB,
+    slot: u64,
+    blk_idx: u16,
+    tx: &MultiEraTx<'_>,
+    stake_hash: &[u8; 28],
+) -> miette::Result<()> {
+    let is_wallet = |cred: &StakeCredential| match cred {
+        StakeCredential::AddrKeyhash(h) => **h == *stake_hash,
+        StakeCredential::Scripthash(_) => false,
+    };
+
+    for cert in tx.certs() {
+        let event = match cert.as_alonzo() {
+            Some(Certificate::StakeRegistration(c)) if is_wallet(c) => {
+                (StakeKind::Registration, None)
+            }
+            Some(Certificate::StakeDeregistration(c)) if is_wallet(c) => {
+                (StakeKind::Deregistration, None)
+            }
+            Some(Certificate::StakeDelegation(c, pool)) if is_wallet(c) => {
+                (StakeKind::Delegation, Some(pool.to_vec()))
+            }
+            _ => continue,
+        };
+
+        wallet_db
+            .insert_stake_event(*tx.hash(), slot, blk_idx, event.0, event.1, None)
+            .await
+            .into_diagnostic()?;
+    }
+
+    let withdrawals = tx.withdrawals();
+    let withdrawals: Vec<(&[u8], u64)> = withdrawals.collect();
+
+    for (account, amount) in withdrawals {
+        let is_wallet_account = match Address::from_bytes(account) {
+            Ok(Address::Stake(x)) => match x.payload() {
+                StakePayload::Stake(h) => **h == *stake_hash,
+                StakePayload::Script(_) => false,
+            },
+            _ => false,
+        };
+
+        if is_wallet_account {
+            wallet_db
+                .insert_stake_event(
+                    *tx.hash(),
+                    slot,
+                    blk_idx,
+                    StakeKind::Withdrawal,
+                    None,
+                    Some(amount),
+                )
+                .await
+                .into_diagnostic()?;
+        }
+    }
+
+    Ok(())
+}
+
+fn output_pkh(txo: &MultiEraOutput<'_>) -> Option<[u8; 28]> {
+    match txo.address().unwrap() {
         Address::Shelley(a) => match a.payment() {
-            ShelleyPaymentPart::Key(h) => *h,
-            _ => return false,
+            ShelleyPaymentPart::Key(h) => Some(**h),
+            _ => None,
         },
-        _ => return false,
-    };
+        _ => None,
+    }
+}
 
-    pkhs.contains(&controlling_pkh)
+fn output_controlled_by_wallet(txo: &MultiEraOutput<'_>, watched: &Watched) -> bool {
+    match txo.address().unwrap() {
+        Address::Shelley(a) => match a.payment() {
+            ShelleyPaymentPart::Key(h) => watched.pkhs.contains(h),
+            ShelleyPaymentPart::Script(h) => watched.scripts.contains(h),
+        },
+        _ => false,
+    }
 }